@@ -13,25 +13,32 @@
 ........................................................................................
 */
 
+use std::path::PathBuf;
 use std::process::Command;
+use std::str::FromStr;
 use std::thread;
 
-use wlroots::terminate as wlr_terminate;
+use wlroots::{terminate as wlr_terminate, Area};
 
 use common::command_type::CommandType;
+use common::ipc::IpcEvent;
 use compositor::commands::Command as CompositorCommand;
+use compositor::decoration;
+use compositor::screenshot::{self, ScreenshotTarget};
 use compositor::ComfyKernel;
+use config::keybinding::DEFAULT_MODE;
 use config::Config;
 use layout::LayoutDirection;
 
 pub struct CommandInterpreter;
 impl CommandInterpreter {
-	pub fn execute(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
+	/// Executes the provided command. The errors are logged, then returned so the IPC clients can be told about them.
+	pub fn execute(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
 		info!(
 			"Executing command: {:?} with args: {:?}",
 			command.command_type, command.args
 		);
-		match command.command_type {
+		let result = match command.command_type {
 			CommandType::SetInsertDirectionUp => handle_set_insert_direction_up(command, comfy_kernel),
 			CommandType::SetInsertDirectionDown => handle_set_insert_direction_down(command, comfy_kernel),
 			CommandType::SetInsertDirectionLeft => handle_set_insert_direction_left(command, comfy_kernel),
@@ -51,8 +58,22 @@ impl CommandInterpreter {
 			CommandType::Exec => handle_exec(command, comfy_kernel),
 			CommandType::CloseActiveWindow => handle_close_active_window(command, comfy_kernel),
 			CommandType::Terminate => handle_terminate(command, comfy_kernel),
-			_ => error!("Command type unknown!"),
+			CommandType::SwitchToWorkspace => handle_switch_to_workspace(command, comfy_kernel),
+			CommandType::MoveActiveWindowToWorkspace => handle_move_active_window_to_workspace(command, comfy_kernel),
+			CommandType::GrowActiveWindow => handle_grow_active_window(command, comfy_kernel),
+			CommandType::ShrinkActiveWindow => handle_shrink_active_window(command, comfy_kernel),
+			CommandType::ToggleActiveWindowFloating => handle_toggle_active_window_floating(command, comfy_kernel),
+			CommandType::Screenshot => handle_screenshot(command, comfy_kernel),
+			CommandType::FocusOutput => handle_focus_output(command, comfy_kernel),
+			CommandType::MoveActiveWindowToOutput => handle_move_active_window_to_output(command, comfy_kernel),
+			CommandType::EnterMode => handle_enter_mode(command, comfy_kernel),
+			CommandType::SwitchKeyboardLayout => handle_switch_keyboard_layout(command, comfy_kernel),
+			_ => Err("Command type unknown!".to_string()),
+		};
+		if let Err(ref e) = result {
+			error!("{}", e);
 		}
+		result
 	}
 }
 
@@ -71,20 +92,24 @@ impl CommandInterpreter {
 ........................................................................
 */
 
-fn handle_set_insert_direction_up(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
+fn handle_set_insert_direction_up(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
 	comfy_kernel.set_cursor_direction(LayoutDirection::Up);
+	Ok(())
 }
 
-fn handle_set_insert_direction_down(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
+fn handle_set_insert_direction_down(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
 	comfy_kernel.set_cursor_direction(LayoutDirection::Down);
+	Ok(())
 }
 
-fn handle_set_insert_direction_left(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
+fn handle_set_insert_direction_left(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
 	comfy_kernel.set_cursor_direction(LayoutDirection::Left);
+	Ok(())
 }
 
-fn handle_set_insert_direction_right(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
+fn handle_set_insert_direction_right(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
 	comfy_kernel.set_cursor_direction(LayoutDirection::Right);
+	Ok(())
 }
 
 /*
@@ -102,20 +127,20 @@ fn handle_set_insert_direction_right(_: &CompositorCommand, comfy_kernel: &mut C
 ..................................................
 */
 
-fn handle_move_active_focus_up(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Up);
+fn handle_move_active_focus_up(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Up)
 }
 
-fn handle_move_active_focus_down(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Down);
+fn handle_move_active_focus_down(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Down)
 }
 
-fn handle_move_active_focus_left(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Left);
+fn handle_move_active_focus_left(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Left)
 }
 
-fn handle_move_active_focus_right(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Right);
+fn handle_move_active_focus_right(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_cursor_in_active_output(LayoutDirection::Right)
 }
 
 /*
@@ -133,20 +158,20 @@ fn handle_move_active_focus_right(_: &CompositorCommand, comfy_kernel: &mut Comf
 ..................................................
 */
 
-fn handle_move_active_window_up(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_active_window(LayoutDirection::Up);
+fn handle_move_active_window_up(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_active_window(LayoutDirection::Up)
 }
 
-fn handle_move_active_window_down(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_active_window(LayoutDirection::Down);
+fn handle_move_active_window_down(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_active_window(LayoutDirection::Down)
 }
 
-fn handle_move_active_window_left(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_active_window(LayoutDirection::Left);
+fn handle_move_active_window_left(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_active_window(LayoutDirection::Left)
 }
 
-fn handle_move_active_window_right(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.move_active_window(LayoutDirection::Right);
+fn handle_move_active_window_right(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.move_active_window(LayoutDirection::Right)
 }
 
 
@@ -165,12 +190,12 @@ fn handle_move_active_window_right(_: &CompositorCommand, comfy_kernel: &mut Com
 ........................................
 */
 
-fn handle_put_active_window_to_stack(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.put_active_window_to_stack();
+fn handle_put_active_window_to_stack(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.put_active_window_to_stack()
 }
 
-fn handle_pop_window_from_stack(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.pop_window_from_stack();
+fn handle_pop_window_from_stack(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.pop_window_from_stack()
 }
 
 /*
@@ -188,8 +213,21 @@ fn handle_pop_window_from_stack(_: &CompositorCommand, comfy_kernel: &mut ComfyK
 ................................................................................
 */
 
-fn handle_toggle_active_window_fullscreen(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	comfy_kernel.toggle_active_window_fullscreen();
+fn handle_toggle_active_window_fullscreen(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.toggle_active_window_fullscreen()
+}
+
+/*
+.######..##.......####....####...######..######..##..##...####..
+.##......##......##..##..##..##....##......##....###.##..##.....
+.####....##......##..##..######....##......##....##.###..##.###.
+.##......##......##..##..##..##....##......##....##..##..##..##.
+.##......######...####...##..##....##....######..##..##...####..
+................................................................
+*/
+
+fn handle_toggle_active_window_floating(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	comfy_kernel.toggle_active_window_floating()
 }
 
 /*
@@ -207,14 +245,32 @@ fn handle_toggle_active_window_fullscreen(_: &CompositorCommand, comfy_kernel: &
 ................................................
 */
 
-fn handle_reload_config(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
+fn handle_reload_config(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
 	match Config::reload_config() {
 		Ok(config) => {
+			// ? Applying the keymap brings the keyboard back to it's first layout, so it is only done on changes
+			let keyboard_config_changed = config.global.keyboard != comfy_kernel.config.global.keyboard;
 			comfy_kernel.config = config;
+			if keyboard_config_changed {
+				comfy_kernel.apply_keyboard_config();
+			}
 			comfy_kernel.should_load_wallpaper = true;
-			info!("Sucessfully reloaded the user's config")
+			comfy_kernel.apply_theme_animations();
+			comfy_kernel.apply_output_configs();
+			decoration::renegotiate_all_modes(comfy_kernel);
+			comfy_kernel.pending_key_sequence = None;
+			// ? The mode may have been removed from the new keybindings
+			if !comfy_kernel.config.keybindings.has_mode(&comfy_kernel.binding_mode) {
+				comfy_kernel.enter_binding_mode(DEFAULT_MODE).ok();
+			}
+			comfy_kernel.emit_event(IpcEvent::ConfigReloaded);
+			info!("Sucessfully reloaded the user's config");
+			Ok(())
 		}
-		Err(e) => error!("Could not reload the user's config, no changes we're applied : {}", e),
+		Err(e) => Err(format!(
+			"Could not reload the user's config, no changes we're applied : {}",
+			e
+		)),
 	}
 }
 
@@ -227,11 +283,11 @@ fn handle_reload_config(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
 ................................
 */
 
-fn handle_exec(command: &CompositorCommand, _: &mut ComfyKernel) {
+fn handle_exec(command: &CompositorCommand, _: &mut ComfyKernel) -> Result<(), String> {
 	let command_clone = command.clone();
 	let nb_of_arguments = command_clone.args.len();
 	if nb_of_arguments == 0 {
-		error!("Tried to execute an 'Exec' command without providing any arguments!");
+		Err("Tried to execute an 'Exec' command without providing any arguments!".to_string())
 	} else {
 		thread::spawn(move || {
 			let executable = &command_clone.args[0];
@@ -249,6 +305,7 @@ fn handle_exec(command: &CompositorCommand, _: &mut ComfyKernel) {
 				Err(e) => error!("The command {} failed with: {}", command_clone.args.join(" "), e),
 			};
 		});
+		Ok(())
 	}
 }
 
@@ -267,9 +324,255 @@ fn handle_exec(command: &CompositorCommand, _: &mut ComfyKernel) {
 ..................................................
 */
 
-fn handle_close_active_window(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) {
-	if let Some(window) = comfy_kernel.get_active_window() {
-		window.close();
+fn handle_close_active_window(_: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	match comfy_kernel.get_active_window() {
+		Some(window) => {
+			window.close();
+			Ok(())
+		}
+		None => Err("No active window to close".to_string()),
+	}
+}
+
+/*
+.##...##...####...#####...##..##...####...#####....####....####...######.
+.##...##..##..##..##..##..##.##...##......##..##..##..##..##..##..##.....
+.##.#.##..##..##..#####...####.....####...#####...######..##......####...
+.#######..##..##..##..##..##.##.......##..##......##..##..##..##..##.....
+..##.##....####...##..##..##..##...####...##......##..##...####...######.
+.........................................................................
+*/
+
+fn handle_switch_to_workspace(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	let workspace_number = parse_workspace_number(command, comfy_kernel)?;
+	comfy_kernel.switch_to_workspace(workspace_number)
+}
+
+fn handle_move_active_window_to_workspace(
+	command: &CompositorCommand,
+	comfy_kernel: &mut ComfyKernel,
+) -> Result<(), String> {
+	let workspace_number = parse_workspace_number(command, comfy_kernel)?;
+	comfy_kernel.move_active_window_to_workspace(workspace_number)
+}
+
+/// Parses the first argument of the provided command as a workspace number, or as `next`/`prev` for the workspaces
+/// around the one displayed on the active output.
+fn parse_workspace_number(command: &CompositorCommand, comfy_kernel: &ComfyKernel) -> Result<u32, String> {
+	match command.args.first() {
+		Some(workspace_str) if workspace_str == "next" || workspace_str == "prev" => {
+			match comfy_kernel.output_data_map.get(&comfy_kernel.active_output_name) {
+				Some(output_data) if workspace_str == "next" => Ok(output_data.next_workspace_number()),
+				Some(output_data) => Ok(output_data.previous_workspace_number()),
+				None => Err(format!("No active output to find the {} workspace", workspace_str)),
+			}
+		}
+		Some(workspace_number_str) => workspace_number_str
+			.parse::<u32>()
+			.map_err(|e| format!("Invalid workspace number '{}': {}", workspace_number_str, e)),
+		None => Err(format!(
+			"Tried to execute a '{}' command without providing a workspace number!",
+			command.command_type.to_string()
+		)),
+	}
+}
+
+/*
+..####...##..##..######..#####...##..##..######.
+.##..##..##..##....##....##..##..##..##....##...
+.##..##..##..##....##....#####...##..##....##...
+.##..##..##..##....##....##......##..##....##...
+..####....####.....##....##.......####.....##...
+................................................
+*/
+
+fn handle_focus_output(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	let (output_name, _) = parse_output_target(command, comfy_kernel)?;
+	comfy_kernel.focus_output(&output_name)
+}
+
+fn handle_move_active_window_to_output(
+	command: &CompositorCommand,
+	comfy_kernel: &mut ComfyKernel,
+) -> Result<(), String> {
+	let (output_name, insert_direction) = parse_output_target(command, comfy_kernel)?;
+	comfy_kernel.move_active_window_to_output(&output_name, &insert_direction)
+}
+
+/// Finds the output targeted by the first argument of the provided command, either it's name or a direction from the
+/// active output. Also returns the direction in which a moved window is inserted: from the side it comes from, or the
+/// insert direction of the layout if the output was targeted by it's name.
+fn parse_output_target(
+	command: &CompositorCommand,
+	comfy_kernel: &ComfyKernel,
+) -> Result<(String, LayoutDirection), String> {
+	let target_str = match command.args.first() {
+		Some(target_str) => target_str,
+		None => {
+			return Err(format!(
+				"Tried to execute a '{}' command without providing an output!",
+				command.command_type.to_string()
+			))
+		}
+	};
+	if comfy_kernel.output_data_map.contains_key(target_str) {
+		return Ok((target_str.clone(), comfy_kernel.cursor_direction.clone()));
+	}
+	let direction = LayoutDirection::from_str(target_str).map_err(|_| {
+		format!(
+			"Unknown output '{}', expected an output name or a direction",
+			target_str
+		)
+	})?;
+	match comfy_kernel.find_output_next_to_active_one(&direction) {
+		Some(output_name) => Ok((output_name, direction.get_opposite())),
+		None => Err(format!(
+			"No output found {} of {}",
+			target_str, comfy_kernel.active_output_name
+		)),
+	}
+}
+
+/*
+.##...##...####...#####...######.
+.###.###..##..##..##..##..##.....
+.##.#.##..##..##..##..##..####...
+.##...##..##..##..##..##..##.....
+.##...##...####...#####...######.
+.................................
+*/
+
+fn handle_enter_mode(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	match command.args.first() {
+		Some(mode_name) => comfy_kernel.enter_binding_mode(mode_name),
+		None => Err(format!(
+			"Tried to execute a '{}' command without providing a mode!",
+			command.command_type.to_string()
+		)),
+	}
+}
+
+/*
+.##..##..######..##..##..#####....####....####...#####...#####..
+.##.##...##.......####...##..##..##..##..##..##..##..##..##..##.
+.####....####......##....#####...##..##..######..#####...##..##.
+.##.##...##........##....##..##..##..##..##..##..##..##..##..##.
+.##..##..######....##....#####....####...##..##..##..##..#####..
+................................................................
+*/
+
+fn handle_switch_keyboard_layout(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	let layout_index = parse_keyboard_layout_index(command, comfy_kernel)?;
+	comfy_kernel.set_keyboard_layout(layout_index)
+}
+
+/// Parses the first argument of the provided command as the index of a layout of the keyboard configuration, or as
+/// `next`/`prev` for the layouts around the active one.
+fn parse_keyboard_layout_index(command: &CompositorCommand, comfy_kernel: &ComfyKernel) -> Result<u32, String> {
+	let nb_layouts = comfy_kernel.config.global.keyboard.nb_layouts();
+	match command.args.first() {
+		Some(layout_str) if layout_str == "next" || layout_str == "prev" => match comfy_kernel.active_keyboard_layout() {
+			Some(active_layout) if layout_str == "next" => Ok((active_layout + 1) % nb_layouts),
+			Some(active_layout) => Ok((active_layout + nb_layouts - 1) % nb_layouts),
+			None => Err(format!("No keyboard to switch to the {} layout", layout_str)),
+		},
+		Some(layout_index_str) => match layout_index_str.parse::<u32>() {
+			Ok(layout_index) if layout_index < nb_layouts => Ok(layout_index),
+			Ok(layout_index) => Err(format!(
+				"The keyboard has {} layouts, no layout at the index {}",
+				nb_layouts, layout_index
+			)),
+			Err(e) => Err(format!("Invalid keyboard layout index '{}': {}", layout_index_str, e)),
+		},
+		None => Err(format!(
+			"Tried to execute a '{}' command without providing a layout!",
+			command.command_type.to_string()
+		)),
+	}
+}
+
+/*
+.#####...######...####...######..######..######.
+.##..##..##......##........##.......##...##.....
+.#####...####.....####.....##......##....####...
+.##..##..##..........##....##.....##.....##.....
+.##..##..######...####...######..######..######.
+................................................
+*/
+
+/// Weight added or removed when no amount is provided to the resize commands.
+const DEFAULT_RESIZE_AMOUNT: f32 = 0.1;
+
+fn handle_grow_active_window(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	let (direction, amount) = parse_resize_args(command)?;
+	comfy_kernel.resize_active_window(direction, amount)
+}
+
+fn handle_shrink_active_window(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	let (direction, amount) = parse_resize_args(command)?;
+	comfy_kernel.resize_active_window(direction, -amount)
+}
+
+/// Parses the direction and the optional amount of the resize commands. (Example: `grow_active_window left 0.2`)
+fn parse_resize_args(command: &CompositorCommand) -> Result<(LayoutDirection, f32), String> {
+	let direction = match command.args.first() {
+		Some(direction_str) => LayoutDirection::from_str(direction_str)?,
+		None => {
+			return Err(format!(
+				"Tried to execute a '{}' command without providing a direction!",
+				command.command_type.to_string()
+			))
+		}
+	};
+	let amount = match command.args.get(1) {
+		Some(amount_str) => amount_str
+			.parse::<f32>()
+			.map_err(|e| format!("Invalid resize amount '{}': {}", amount_str, e))?,
+		None => DEFAULT_RESIZE_AMOUNT,
+	};
+	if amount <= 0.0 {
+		return Err(format!("The resize amount must be positive, got {}", amount));
+	}
+	Ok((direction, amount))
+}
+
+/*
+..####....####...#####...######..######..##..##...####...##..##...####...######.
+.##......##..##..##..##..##......##......###.##..##......##..##..##..##....##...
+..####...##......#####...####....####....##.###...####...######..##..##....##...
+.....##..##..##..##..##..##......##......##..##......##..##..##..##..##....##...
+..####....####...##..##..######..######..##..##...####...##..##...####.....##...
+................................................................................
+*/
+
+fn handle_screenshot(command: &CompositorCommand, comfy_kernel: &mut ComfyKernel) -> Result<(), String> {
+	let (target, region_option, path) = parse_screenshot_args(command)?;
+	comfy_kernel.request_screenshot(&target, region_option, path)
+}
+
+/// Parses the target, the region and the path of a screenshot command. The target is the active output when omitted.
+/// (Example: `screenshot window shot.png` or `screenshot region 10,20 640x480 shot.png`)
+fn parse_screenshot_args(command: &CompositorCommand) -> Result<(ScreenshotTarget, Option<Area>, PathBuf), String> {
+	let (target, other_args) = match command.args.len() {
+		0 => return Err("Tried to take a screenshot without providing a path!".to_string()),
+		1 => (ScreenshotTarget::Output, &command.args[..]),
+		_ => (
+			ScreenshotTarget::from_str(&command.args[0])
+				.map_err(|_| format!("Unknown screenshot target: {}", command.args[0]))?,
+			&command.args[1..],
+		),
+	};
+	match (&target, other_args) {
+		(ScreenshotTarget::Region, [origin_str, size_str, path_str]) => Ok((
+			target,
+			Some(screenshot::parse_region(origin_str, size_str)?),
+			PathBuf::from(path_str),
+		)),
+		(ScreenshotTarget::Region, _) => {
+			Err("A region screenshot expects: region <x>,<y> <width>x<height> <path>".to_string())
+		}
+		(_, [path_str]) => Ok((target, None, PathBuf::from(path_str))),
+		_ => Err(format!("Unexpected screenshot arguments: {:?}", command.args)),
 	}
 }
 
@@ -282,7 +585,8 @@ fn handle_close_active_window(_: &CompositorCommand, comfy_kernel: &mut ComfyKer
 .........................................................................
 */
 
-fn handle_terminate(_: &CompositorCommand, _: &mut ComfyKernel) {
+fn handle_terminate(_: &CompositorCommand, _: &mut ComfyKernel) -> Result<(), String> {
 	info!("Goodbye!");
 	wlr_terminate();
+	Ok(())
 }
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::ptr;
+
+use wayland_sys::common::wl_list;
+use wayland_sys::server::signal::wl_signal_add;
+use wayland_sys::server::{wl_display, wl_listener, wl_notify_func_t, WAYLAND_SERVER_HANDLE};
+use wlroots::wlroots_sys::wlr_xdg_toplevel_decoration_v1_mode::{
+	WLR_XDG_TOPLEVEL_DECORATION_V1_MODE_CLIENT_SIDE as CLIENT_SIDE_MODE,
+	WLR_XDG_TOPLEVEL_DECORATION_V1_MODE_SERVER_SIDE as SERVER_SIDE_MODE,
+};
+use wlroots::wlroots_sys::{
+	wlr_xdg_decoration_manager_v1_create, wlr_xdg_surface, wlr_xdg_surface_role, wlr_xdg_toplevel_decoration_v1,
+	wlr_xdg_toplevel_decoration_v1_mode, wlr_xdg_toplevel_decoration_v1_set_mode,
+};
+
+use compositor::shell_handle::ShellHandle;
+use compositor::ComfyKernel;
+use config::global::DecorationPolicy;
+use ipc::with_comfy_kernel;
+
+/*
+.#####...######...####....####...#####....####...######..######...####...##..##.
+.##..##..##......##..##..##..##..##..##..##..##....##......##....##..##..###.##.
+.##..##..####....##......##..##..#####...######....##......##....##..##..##.###.
+.##..##..##......##..##..##..##..##..##..##..##....##......##....##..##..##..##.
+.#####...######...####....####...##..##..##..##....##....######...####...##..##.
+................................................................................
+*/
+
+/// Decoration of a top level negotiated with it's client.
+struct ToplevelDecoration {
+	xdg_surface: *mut wlr_xdg_surface,
+	/// The client agreed to draw it's own decorations.
+	is_client_side: bool,
+	request_mode_listener: Box<wl_listener>,
+	/// The app id of the top level is only known once it's mapped, the mode is negotiated again at that point.
+	map_listener: Box<wl_listener>,
+	destroy_listener: Box<wl_listener>,
+}
+
+/// Negotiates the decorations of the windows through xdg-decoration-unstable-v1. The mode of each window is decided
+/// by the decoration policy of the global config, for the app id of the window.
+pub struct XdgDecorationManager {
+	/// Registered for as long as the manager exists, which is destroyed with the display.
+	_new_toplevel_decoration_listener: Box<wl_listener>,
+	/// Decorations created by the clients, by the address of their wlroots structure.
+	toplevel_decorations: HashMap<usize, ToplevelDecoration>,
+}
+
+impl XdgDecorationManager {
+	/// Advertises the protocol on the display, then starts listening for the decorations created by the clients.
+	pub fn create(display: *mut wl_display) -> Result<Self, String> {
+		let manager = unsafe { wlr_xdg_decoration_manager_v1_create(display as *mut _) };
+		if manager.is_null() {
+			return Err("Could not create the xdg-decoration manager".to_string());
+		}
+		let mut new_toplevel_decoration_listener = new_listener(handle_new_toplevel_decoration);
+		unsafe {
+			wl_signal_add(
+				&mut (*manager).events.new_toplevel_decoration as *mut _ as *mut _,
+				new_toplevel_decoration_listener.as_mut(),
+			);
+		}
+		Ok(XdgDecorationManager {
+			_new_toplevel_decoration_listener: new_toplevel_decoration_listener,
+			toplevel_decorations: HashMap::new(),
+		})
+	}
+
+	/// Returns true if the client of the provided shell agreed to draw it's own decorations.
+	pub fn has_client_side_decorations(&self, shell_handle: &ShellHandle) -> bool {
+		match shell_handle.xdg_surface_ptr() {
+			Some(xdg_surface) => self
+				.toplevel_decorations
+				.values()
+				.any(|decoration| decoration.xdg_surface == xdg_surface && decoration.is_client_side),
+			None => false,
+		}
+	}
+
+	/// Returns the decorations of the provided xdg surface, there is usually only one.
+	fn decorations_of(&self, xdg_surface: *mut wlr_xdg_surface) -> Vec<*mut wlr_xdg_toplevel_decoration_v1> {
+		self
+			.toplevel_decorations
+			.iter()
+			.filter(|&(_, decoration)| decoration.xdg_surface == xdg_surface)
+			.map(|(address, _)| *address as *mut wlr_xdg_toplevel_decoration_v1)
+			.collect()
+	}
+
+	unsafe fn add_toplevel_decoration(&mut self, decoration: *mut wlr_xdg_toplevel_decoration_v1) {
+		let mut toplevel_decoration = ToplevelDecoration {
+			xdg_surface: (*decoration).surface,
+			is_client_side: false,
+			request_mode_listener: new_listener(handle_decoration_mode_request),
+			map_listener: new_listener(handle_decorated_surface_mapped),
+			destroy_listener: new_listener(handle_decoration_destroyed),
+		};
+		wl_signal_add(
+			&mut (*decoration).events.request_mode as *mut _ as *mut _,
+			toplevel_decoration.request_mode_listener.as_mut(),
+		);
+		wl_signal_add(
+			&mut (*(*decoration).surface).events.map as *mut _ as *mut _,
+			toplevel_decoration.map_listener.as_mut(),
+		);
+		wl_signal_add(
+			&mut (*decoration).events.destroy as *mut _ as *mut _,
+			toplevel_decoration.destroy_listener.as_mut(),
+		);
+		self
+			.toplevel_decorations
+			.insert(decoration as usize, toplevel_decoration);
+	}
+
+	unsafe fn remove_toplevel_decoration(
+		&mut self,
+		decoration: *mut wlr_xdg_toplevel_decoration_v1,
+	) -> Option<ToplevelDecoration> {
+		let mut toplevel_decoration = self.toplevel_decorations.remove(&(decoration as usize))?;
+		ffi_dispatch!(
+			WAYLAND_SERVER_HANDLE,
+			wl_list_remove,
+			&mut toplevel_decoration.request_mode_listener.link
+		);
+		ffi_dispatch!(
+			WAYLAND_SERVER_HANDLE,
+			wl_list_remove,
+			&mut toplevel_decoration.map_listener.link
+		);
+		ffi_dispatch!(
+			WAYLAND_SERVER_HANDLE,
+			wl_list_remove,
+			&mut toplevel_decoration.destroy_listener.link
+		);
+		Some(toplevel_decoration)
+	}
+}
+
+/// Negotiates the mode of every decoration again, after the decoration policy of the config changed.
+pub fn renegotiate_all_modes(comfy_kernel: &mut ComfyKernel) {
+	let decorations: Vec<*mut wlr_xdg_toplevel_decoration_v1> = match comfy_kernel.decoration_manager {
+		Some(ref decoration_manager) => decoration_manager
+			.toplevel_decorations
+			.keys()
+			.map(|address| *address as *mut wlr_xdg_toplevel_decoration_v1)
+			.collect(),
+		None => return,
+	};
+	for decoration in decorations {
+		unsafe { negotiate_mode(comfy_kernel, decoration) };
+	}
+}
+
+/// Returns the mode of the decorations according to the policy of the window and the mode requested by it's client.
+fn select_mode(
+	policy: &DecorationPolicy,
+	requested_mode: wlr_xdg_toplevel_decoration_v1_mode,
+) -> wlr_xdg_toplevel_decoration_v1_mode {
+	match policy {
+		DecorationPolicy::ForceServer => SERVER_SIDE_MODE,
+		DecorationPolicy::PreferClient if requested_mode == SERVER_SIDE_MODE => SERVER_SIDE_MODE,
+		DecorationPolicy::PreferClient => CLIENT_SIDE_MODE,
+	}
+}
+
+/// Sends the mode selected for the decoration to it's client, then updates the borders of the window.
+unsafe fn negotiate_mode(comfy_kernel: &mut ComfyKernel, decoration: *mut wlr_xdg_toplevel_decoration_v1) {
+	let xdg_surface = (*decoration).surface;
+	let app_id = toplevel_app_id(xdg_surface);
+	let mode = select_mode(
+		comfy_kernel.config.global.decoration_policy_for(&app_id),
+		(*decoration).client_pending_mode,
+	);
+	wlr_xdg_toplevel_decoration_v1_set_mode(decoration, mode);
+	debug!("Negotiated the decoration mode of {}: {:?}", app_id, mode);
+
+	let is_client_side = mode == CLIENT_SIDE_MODE;
+	if let Some(ref mut decoration_manager) = comfy_kernel.decoration_manager {
+		if let Some(toplevel_decoration) = decoration_manager.toplevel_decorations.get_mut(&(decoration as usize)) {
+			toplevel_decoration.is_client_side = is_client_side;
+		}
+	}
+	comfy_kernel.set_client_side_decorations(xdg_surface, is_client_side);
+}
+
+/// Returns the app id of the top level of the provided xdg surface, empty if the client didn't set it yet.
+unsafe fn toplevel_app_id(xdg_surface: *mut wlr_xdg_surface) -> String {
+	if (*xdg_surface).role != wlr_xdg_surface_role::WLR_XDG_SURFACE_ROLE_TOPLEVEL {
+		return String::new();
+	}
+	let app_id = (*(*xdg_surface).__bindgen_anon_1.toplevel).app_id;
+	if app_id.is_null() {
+		String::new()
+	} else {
+		CStr::from_ptr(app_id).to_string_lossy().into_owned()
+	}
+}
+
+/// Allocates a listener calling the provided function, boxed so it's address stays the same once registered.
+pub fn new_listener(notify: wl_notify_func_t) -> Box<wl_listener> {
+	Box::new(wl_listener {
+		link: wl_list {
+			prev: ptr::null_mut(),
+			next: ptr::null_mut(),
+		},
+		notify,
+	})
+}
+
+/*
+.##..##...####...##..##..#####...##......######..#####....####..
+.##..##..##..##..###.##..##..##..##......##......##..##..##.....
+.######..######..##.###..##..##..##......####....#####....####..
+.##..##..##..##..##..##..##..##..##......##......##..##......##.
+.##..##..##..##..##..##..#####...######..######..##..##...####..
+................................................................
+*/
+
+/// Called when a client creates the decoration object of one of it's top levels.
+unsafe extern "C" fn handle_new_toplevel_decoration(_listener: *mut wl_listener, data: *mut c_void) {
+	let decoration = data as *mut wlr_xdg_toplevel_decoration_v1;
+	with_comfy_kernel(|comfy_kernel| {
+		if let Some(ref mut decoration_manager) = comfy_kernel.decoration_manager {
+			decoration_manager.add_toplevel_decoration(decoration);
+		}
+		negotiate_mode(comfy_kernel, decoration);
+	});
+}
+
+/// Called when a client asks for another decoration mode, or to let the compositor decide it.
+unsafe extern "C" fn handle_decoration_mode_request(_listener: *mut wl_listener, data: *mut c_void) {
+	let decoration = data as *mut wlr_xdg_toplevel_decoration_v1;
+	with_comfy_kernel(|comfy_kernel| negotiate_mode(comfy_kernel, decoration));
+}
+
+/// Called when the top level of a decoration is mapped, it's app id is set by then.
+unsafe extern "C" fn handle_decorated_surface_mapped(_listener: *mut wl_listener, data: *mut c_void) {
+	let xdg_surface = data as *mut wlr_xdg_surface;
+	with_comfy_kernel(|comfy_kernel| {
+		let decorations = match comfy_kernel.decoration_manager {
+			Some(ref decoration_manager) => decoration_manager.decorations_of(xdg_surface),
+			None => return,
+		};
+		for decoration in decorations {
+			negotiate_mode(comfy_kernel, decoration);
+		}
+	});
+}
+
+/// Called when the decoration object or it's top level is destroyed. The window, if still alive, gets it's borders
+/// back.
+unsafe extern "C" fn handle_decoration_destroyed(_listener: *mut wl_listener, data: *mut c_void) {
+	let decoration = data as *mut wlr_xdg_toplevel_decoration_v1;
+	with_comfy_kernel(|comfy_kernel| {
+		let toplevel_decoration_option = match comfy_kernel.decoration_manager {
+			Some(ref mut decoration_manager) => decoration_manager.remove_toplevel_decoration(decoration),
+			None => None,
+		};
+		if let Some(toplevel_decoration) = toplevel_decoration_option {
+			if toplevel_decoration.is_client_side {
+				comfy_kernel.set_client_side_decorations(toplevel_decoration.xdg_surface, false);
+			}
+		}
+	});
+}
@@ -0,0 +1,98 @@
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+use wayland_sys::server::{wl_event_loop, WAYLAND_SERVER_HANDLE};
+use wlroots::wlroots_sys::{wlr_keyboard_notify_modifiers, wlr_keyboard_set_keymap};
+
+use compositor::output::apply_output_change;
+use compositor::output_management::publish_output_states;
+use ipc::with_comfy_kernel;
+
+/*
+.#####...######..######..######..#####...#####...######..#####..
+.##..##..##......##......##......##..##..##..##..##......##..##.
+.##..##..####....####....####....#####...#####...####....##..##.
+.##..##..##......##......##......##..##..##..##..##......##..##.
+.#####...######..##......######..##..##..##..##..######..#####..
+................................................................
+*/
+
+// Some wlroots calls emit their events right away (Example: setting the mode of an output or the keymap of a keyboard). The handlers of those
+// events borrow the compositor, which panics if it's already borrowed by the handler making the call. Such calls are
+// deferred until the event loop is idle, then made while nothing is borrowed.
+
+/// Action of the kernel run once the event loop is idle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeferredAction {
+	/// Applies the configuration of each output, see `ComfyKernel::prepare_output_changes`.
+	ApplyOutputConfigs,
+	/// Sends the state of the outputs to the clients of wlr-output-management, once an output was added or removed.
+	PublishOutputStates,
+	/// Sets the keymap described by the keyboard configuration on the keyboard.
+	ApplyKeyboardConfig,
+	/// Makes the keyboard use the layout at the provided index of it's keymap.
+	SwitchKeyboardLayout(u32),
+}
+
+/// Asks the event loop to run the deferred actions of the kernel once it's idle. The idle source is removed by the
+/// event loop after it ran.
+pub fn schedule_deferred_actions(event_loop: *mut wl_event_loop) {
+	let idle_source = unsafe {
+		ffi_dispatch!(
+			WAYLAND_SERVER_HANDLE,
+			wl_event_loop_add_idle,
+			event_loop,
+			handle_deferred_actions,
+			ptr::null_mut()
+		)
+	};
+	if idle_source.is_null() {
+		error!("Could not add the deferred actions to the event loop");
+	}
+}
+
+/// Called by the event loop once it's idle, runs the actions deferred since it was last called.
+unsafe extern "C" fn handle_deferred_actions(_data: *mut c_void) {
+	let mut deferred_actions = Vec::new();
+	with_comfy_kernel(|comfy_kernel| deferred_actions = mem::replace(&mut comfy_kernel.deferred_actions, Vec::new()));
+	for deferred_action in deferred_actions {
+		run_deferred_action(deferred_action);
+	}
+}
+
+/// Runs the action in steps, the kernel is only borrowed before and after the calls emitting events.
+unsafe fn run_deferred_action(deferred_action: DeferredAction) {
+	match deferred_action {
+		DeferredAction::ApplyOutputConfigs => {
+			let mut output_changes = Vec::new();
+			with_comfy_kernel(|comfy_kernel| output_changes = comfy_kernel.prepare_output_changes());
+			for output_change in output_changes.iter() {
+				apply_output_change(output_change);
+			}
+			with_comfy_kernel(|comfy_kernel| comfy_kernel.finish_output_changes(&output_changes));
+		}
+		DeferredAction::PublishOutputStates => with_comfy_kernel(publish_output_states),
+		DeferredAction::ApplyKeyboardConfig => {
+			let mut keymap_change_option = None;
+			with_comfy_kernel(|comfy_kernel| keymap_change_option = comfy_kernel.keyboard_keymap_change());
+			if let Some((keyboard_ptr, keymap)) = keymap_change_option {
+				wlr_keyboard_set_keymap(keyboard_ptr, keymap.get_raw_ptr() as *mut _);
+			}
+		}
+		DeferredAction::SwitchKeyboardLayout(layout_index) => {
+			let mut keyboard_ptr_option = None;
+			with_comfy_kernel(|comfy_kernel| keyboard_ptr_option = comfy_kernel.keyboard_ptr());
+			if let Some(keyboard_ptr) = keyboard_ptr_option {
+				let modifiers = (*keyboard_ptr).modifiers;
+				wlr_keyboard_notify_modifiers(
+					keyboard_ptr,
+					modifiers.depressed,
+					modifiers.latched,
+					modifiers.locked,
+					layout_index,
+				);
+			}
+		}
+	}
+}
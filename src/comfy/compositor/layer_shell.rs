@@ -0,0 +1,360 @@
+use wlroots::utils::current_time;
+use wlroots::wlroots_sys::{zwlr_layer_shell_v1_layer, zwlr_layer_surface_v1_anchor};
+use wlroots::{
+	project_box as wlr_project_box, Area, CompositorHandle as WLRCompositorHandle,
+	LayerShellHandler as WLRLayerShellHandler, LayerShellManagerHandler as WLRLayerShellManagerHandler,
+	LayerSurfaceHandle as WLRLayerSurfaceHandle, Origin, Renderer as WLRRenderer, Size,
+	SurfaceHandle as WLRSurfaceHandle, SurfaceHandler as WLRSurfaceHandler,
+};
+
+use compositor::ComfyKernel;
+
+/*
+.##.......####...##..##..######..#####..
+.##......##..##...####...##......##..##.
+.##......######....##....####....#####..
+.##......##..##....##....##......##..##.
+.######..##..##....##....######..##..##.
+........................................
+*/
+
+// Layer surfaces (bars, notifications, launchers, etc...) are placed by the compositor on an output from the edges they
+// are anchored to, and are displayed below or above the windows depending on their layer.
+
+const ANCHOR_TOP: u32 = zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_TOP as u32;
+const ANCHOR_BOTTOM: u32 = zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_BOTTOM as u32;
+const ANCHOR_LEFT: u32 = zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_LEFT as u32;
+const ANCHOR_RIGHT: u32 = zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_RIGHT as u32;
+
+/// Layers of an output, from the bottom to the top. The windows are displayed between `Bottom` and `Top`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+	Background,
+	Bottom,
+	Top,
+	Overlay,
+}
+
+impl Layer {
+	/// Every layer, in the order in which their exclusive zones are applied.
+	pub fn all_from_the_top() -> Vec<Layer> {
+		vec![Layer::Overlay, Layer::Top, Layer::Bottom, Layer::Background]
+	}
+}
+
+/// Space left by the surface between itself and the edges it's anchored to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Margin {
+	pub top: i32,
+	pub right: i32,
+	pub bottom: i32,
+	pub left: i32,
+}
+
+/// State requested by the client of a layer surface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerSurfaceState {
+	pub layer: Layer,
+	/// Bitmask of the edges of the output the surface is anchored to.
+	pub anchor: u32,
+	/// Size reserved on the anchored edge, `0` to only avoid the other exclusive zones and `-1` to ignore them.
+	pub exclusive_zone: i32,
+	pub margin: Margin,
+	/// A size of `0` means the surface is stretched between the anchored edges.
+	pub desired_width: i32,
+	pub desired_height: i32,
+	pub keyboard_interactive: bool,
+}
+
+#[derive(Clone)]
+pub struct LayerSurface {
+	pub layer_surface_handle: WLRLayerSurfaceHandle,
+	pub state: LayerSurfaceState,
+	/// Area given to the surface by the last arrangement, in layout coordinates.
+	pub area: Area,
+	/// The surface is displayed. An unmapped surface is still arranged so it's client knows it's size, but it takes
+	/// no space from the windows.
+	pub is_mapped: bool,
+}
+
+impl LayerSurface {
+	pub fn new(layer_surface_handle: WLRLayerSurfaceHandle) -> Option<Self> {
+		let state = read_state(&layer_surface_handle)?;
+		Some(LayerSurface {
+			layer_surface_handle,
+			state,
+			area: Area::new(Origin::new(0, 0), Size::new(0, 0)),
+			is_mapped: false,
+		})
+	}
+
+	/// Reads the state requested by the client. Returns true if it changed since the last time.
+	pub fn refresh_state(&mut self) -> bool {
+		match read_state(&self.layer_surface_handle) {
+			Some(ref state) if *state != self.state => {
+				self.state = state.clone();
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Returns the handle of the main surface of the layer surface.
+	pub fn surface(&self) -> Option<WLRSurfaceHandle> {
+		self
+			.layer_surface_handle
+			.run(|layer_surface| layer_surface.surface())
+			.ok()
+	}
+
+	/// Returns true if the surface is in one of the layers displayed above the windows.
+	pub fn is_above_windows(&self) -> bool {
+		self.state.layer == Layer::Top || self.state.layer == Layer::Overlay
+	}
+
+	/// Places the surface inside the provided bounds, from it's anchor and margins, then removes it's exclusive zone
+	/// from the usable area. The new size is sent to the client.
+	pub fn arrange(&mut self, output_area: &Area, usable_area: &mut Area) {
+		let bounds = if self.state.exclusive_zone == -1 {
+			*output_area
+		} else {
+			*usable_area
+		};
+		self.area = compute_area(&self.state, &bounds);
+		if self.is_mapped {
+			apply_exclusive_zone(&self.state, usable_area);
+		}
+
+		let (width, height) = (self.area.size.width as u32, self.area.size.height as u32);
+		self
+			.layer_surface_handle
+			.run(|layer_surface| layer_surface.configure(width, height))
+			.ok();
+	}
+
+	/// Renders all the surfaces of the layer surface at it's area, on the output whose top left corner is at the
+	/// provided origin.
+	pub fn render(&self, renderer: &mut WLRRenderer, output_origin: Origin) {
+		let x = self.area.origin.x - output_origin.x;
+		let y = self.area.origin.y - output_origin.y;
+		self
+			.layer_surface_handle
+			.run(|layer_surface| {
+				layer_surface.for_each_surface(&mut |surface_handle: WLRSurfaceHandle, sx, sy| {
+					render_surface(renderer, &surface_handle, x + sx, y + sy);
+				})
+			}).ok();
+	}
+}
+
+/// Reads the state requested by the client of the layer surface, `None` if the surface was destroyed.
+fn read_state(layer_surface_handle: &WLRLayerSurfaceHandle) -> Option<LayerSurfaceState> {
+	layer_surface_handle
+		.run(|layer_surface| {
+			// ? The pending state is read to arrange the surface before it's first commit
+			let state = layer_surface.client_pending();
+			let (top, right, bottom, left) = state.margin();
+			LayerSurfaceState {
+				layer: match layer_surface.layer() {
+					zwlr_layer_shell_v1_layer::ZWLR_LAYER_SHELL_V1_LAYER_BACKGROUND => Layer::Background,
+					zwlr_layer_shell_v1_layer::ZWLR_LAYER_SHELL_V1_LAYER_BOTTOM => Layer::Bottom,
+					zwlr_layer_shell_v1_layer::ZWLR_LAYER_SHELL_V1_LAYER_TOP => Layer::Top,
+					zwlr_layer_shell_v1_layer::ZWLR_LAYER_SHELL_V1_LAYER_OVERLAY => Layer::Overlay,
+				},
+				anchor: state.anchor(),
+				exclusive_zone: state.exclusive_zone(),
+				margin: Margin {
+					top: top as i32,
+					right: right as i32,
+					bottom: bottom as i32,
+					left: left as i32,
+				},
+				desired_width: state.desired_width() as i32,
+				desired_height: state.desired_height() as i32,
+				keyboard_interactive: state.keyboard_interactive(),
+			}
+		}).ok()
+}
+
+/// Returns the area of a surface with the provided state inside the bounds.
+fn compute_area(state: &LayerSurfaceState, bounds: &Area) -> Area {
+	let mut area = Area::new(
+		Origin::new(bounds.origin.x, bounds.origin.y),
+		Size::new(state.desired_width, state.desired_height),
+	);
+	let margin = &state.margin;
+
+	let is_anchored_horizontally = state.anchor & (ANCHOR_LEFT | ANCHOR_RIGHT) == ANCHOR_LEFT | ANCHOR_RIGHT;
+	if is_anchored_horizontally && state.desired_width == 0 {
+		area.size.width = bounds.size.width - margin.left - margin.right;
+		area.origin.x += margin.left;
+	} else if state.anchor & ANCHOR_LEFT != 0 && !is_anchored_horizontally {
+		area.origin.x += margin.left;
+	} else if state.anchor & ANCHOR_RIGHT != 0 && !is_anchored_horizontally {
+		area.origin.x += bounds.size.width - area.size.width - margin.right;
+	} else {
+		area.origin.x += (bounds.size.width - area.size.width) / 2;
+	}
+
+	let is_anchored_vertically = state.anchor & (ANCHOR_TOP | ANCHOR_BOTTOM) == ANCHOR_TOP | ANCHOR_BOTTOM;
+	if is_anchored_vertically && state.desired_height == 0 {
+		area.size.height = bounds.size.height - margin.top - margin.bottom;
+		area.origin.y += margin.top;
+	} else if state.anchor & ANCHOR_TOP != 0 && !is_anchored_vertically {
+		area.origin.y += margin.top;
+	} else if state.anchor & ANCHOR_BOTTOM != 0 && !is_anchored_vertically {
+		area.origin.y += bounds.size.height - area.size.height - margin.bottom;
+	} else {
+		area.origin.y += (bounds.size.height - area.size.height) / 2;
+	}
+	area
+}
+
+/// Removes the exclusive zone of the surface from the usable area, on the single edge the surface is anchored to.
+/// A surface anchored to an edge and both of it's perpendicular edges (a bar) is considered as anchored to that edge.
+fn apply_exclusive_zone(state: &LayerSurfaceState, usable_area: &mut Area) {
+	if state.exclusive_zone <= 0 {
+		return;
+	}
+	let margin = &state.margin;
+	let horizontal_edges = ANCHOR_LEFT | ANCHOR_RIGHT;
+	let vertical_edges = ANCHOR_TOP | ANCHOR_BOTTOM;
+	if state.anchor == ANCHOR_TOP || state.anchor == ANCHOR_TOP | horizontal_edges {
+		let size = state.exclusive_zone + margin.top;
+		usable_area.origin.y += size;
+		usable_area.size.height -= size;
+	} else if state.anchor == ANCHOR_BOTTOM || state.anchor == ANCHOR_BOTTOM | horizontal_edges {
+		usable_area.size.height -= state.exclusive_zone + margin.bottom;
+	} else if state.anchor == ANCHOR_LEFT || state.anchor == ANCHOR_LEFT | vertical_edges {
+		let size = state.exclusive_zone + margin.left;
+		usable_area.origin.x += size;
+		usable_area.size.width -= size;
+	} else if state.anchor == ANCHOR_RIGHT || state.anchor == ANCHOR_RIGHT | vertical_edges {
+		usable_area.size.width -= state.exclusive_zone + margin.right;
+	}
+}
+
+#[wlroots_dehandle(surface)]
+fn render_surface(renderer: &mut WLRRenderer, surface_handle: &WLRSurfaceHandle, x: i32, y: i32) {
+	use surface_handle as surface;
+
+	let (surface_width, surface_height) = surface.current_state().size();
+	let transform = renderer.output.get_transform().invert();
+	let output_transform_matrix = renderer.output.transform_matrix();
+	let surface_size = Size::new(
+		surface_width * renderer.output.scale() as i32,
+		surface_height * renderer.output.scale() as i32,
+	);
+	let render_box = Area::new(Origin::new(x, y), surface_size);
+	let matrix = wlr_project_box(render_box, transform, 0.0, output_transform_matrix);
+	if let Some(texture) = surface.texture().as_ref() {
+		renderer.render_texture_with_matrix(texture, matrix);
+	}
+	surface.send_frame_done(current_time());
+}
+
+/*
+.##..##...####...##..##..#####...##......######..#####..
+.##..##..##..##..###.##..##..##..##......##......##..##.
+.######..######..##.###..##..##..##......####....#####..
+.##..##..##..##..##..##..##..##..##......##......##..##.
+.##..##..##..##..##..##..#####...######..######..##..##.
+........................................................
+*/
+
+pub struct LayerShellHandler;
+impl WLRLayerShellHandler for LayerShellHandler {
+	#[wlroots_dehandle(compositor)]
+	fn on_map(&mut self, compositor_handle: WLRCompositorHandle, layer_surface_handle: WLRLayerSurfaceHandle) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		comfy_kernel.set_layer_surface_mapped(&layer_surface_handle, true);
+		comfy_kernel.focus_layer_surface_if_interactive(&layer_surface_handle);
+	}
+
+	/// The surface is kept, it's client may map it again. (Example: a bar being hidden)
+	#[wlroots_dehandle(compositor)]
+	fn on_unmap(&mut self, compositor_handle: WLRCompositorHandle, layer_surface_handle: WLRLayerSurfaceHandle) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		comfy_kernel.set_layer_surface_mapped(&layer_surface_handle, false);
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn destroyed(&mut self, compositor_handle: WLRCompositorHandle, layer_surface_handle: WLRLayerSurfaceHandle) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		comfy_kernel.remove_layer_surface(&layer_surface_handle);
+	}
+}
+
+/// Arranges the output again when the client of a layer surface changes it's state.
+pub struct LayerSurfaceHandler;
+impl WLRSurfaceHandler for LayerSurfaceHandler {
+	#[wlroots_dehandle(compositor)]
+	fn on_commit(&mut self, compositor_handle: WLRCompositorHandle, surface_handle: WLRSurfaceHandle) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		comfy_kernel.refresh_layer_surface(&surface_handle);
+	}
+}
+
+pub struct LayerShellManagerHandler;
+impl WLRLayerShellManagerHandler for LayerShellManagerHandler {
+	#[wlroots_dehandle(compositor)]
+	fn new_surface(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		layer_surface_handle: WLRLayerSurfaceHandle,
+	) -> (Option<Box<WLRLayerShellHandler>>, Option<Box<WLRSurfaceHandler>>) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		comfy_kernel.add_layer_surface(layer_surface_handle);
+		(Some(Box::new(LayerShellHandler)), Some(Box::new(LayerSurfaceHandler)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn generate_state(anchor: u32, exclusive_zone: i32, desired_width: i32, desired_height: i32) -> LayerSurfaceState {
+		LayerSurfaceState {
+			layer: Layer::Top,
+			anchor,
+			exclusive_zone,
+			margin: Margin::default(),
+			desired_width,
+			desired_height,
+			keyboard_interactive: false,
+		}
+	}
+
+	#[test]
+	fn top_bar_is_stretched_and_reserves_its_height() {
+		let output_area = Area::new(Origin::new(0, 0), Size::new(1920, 1080));
+		let state = generate_state(ANCHOR_TOP | ANCHOR_LEFT | ANCHOR_RIGHT, 30, 0, 30);
+		let mut usable_area = output_area;
+
+		assert_eq!(
+			compute_area(&state, &output_area),
+			Area::new(Origin::new(0, 0), Size::new(1920, 30))
+		);
+		apply_exclusive_zone(&state, &mut usable_area);
+		assert_eq!(usable_area, Area::new(Origin::new(0, 30), Size::new(1920, 1050)));
+	}
+
+	#[test]
+	fn unanchored_surface_is_centered_without_exclusive_zone() {
+		let output_area = Area::new(Origin::new(0, 0), Size::new(1920, 1080));
+		let state = generate_state(0, 50, 400, 200);
+		let mut usable_area = output_area;
+
+		assert_eq!(
+			compute_area(&state, &output_area),
+			Area::new(Origin::new(760, 440), Size::new(400, 200))
+		);
+		apply_exclusive_zone(&state, &mut usable_area);
+		assert_eq!(usable_area, output_area);
+	}
+}
@@ -1,41 +1,74 @@
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::env;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::Instant;
 use std::{collections::HashMap, time::Duration};
 
+use wayland_sys::server::wl_event_loop;
 use wlroots::key_events::KeyEvent as WLRKeyEvent;
 use wlroots::pointer_events::AbsoluteMotionEvent;
 use wlroots::{
-	Capability, Compositor as WLRCompositor, CompositorBuilder as WLRCompositorBuilder, Cursor as WLRCursor,
+	Area, Capability, Compositor as WLRCompositor, CompositorBuilder as WLRCompositorBuilder, Cursor as WLRCursor,
 	CursorHandle as WLRCursorHandle, GenericRenderer, KeyboardHandle as WLRKeyboardHandle,
+	LayerSurfaceHandle as WLRLayerSurfaceHandle, Origin, OutputHandle as WLROutputHandle,
 	OutputLayout as WLROutputLayout, OutputLayoutHandle as WLROutputLayoutHandle, Seat as WLRSeat,
-	SeatHandle as WLRSeatHandle, SurfaceHandle as WLRSurfaceHandle, Texture, XCursorManager as WLRXCursorManager,
-	XdgV6ShellState as WLRXdgV6ShellState, XdgV6ShellSurfaceHandle as WLRXdgV6ShellSurfaceHandle,
+	SeatHandle as WLRSeatHandle, Size, SurfaceHandle as WLRSurfaceHandle, Texture, XCursorManager as WLRXCursorManager,
 };
 
+use wlroots::wlroots_sys::wlr_key_state::WLR_KEY_PRESSED;
 use wlroots::wlroots_sys::{
 	protocols::server_decoration::server::org_kde_kwin_server_decoration_manager::Mode as ServerDecorationMode,
-	wlr_axis_orientation, wlr_axis_source,
+	wlr_axis_orientation, wlr_axis_source, wlr_keyboard, wlr_xdg_surface,
 };
+use wlroots::xkbcommon::xkb;
 
 pub mod commands;
+pub mod decoration;
+pub mod deferred;
+pub mod layer_shell;
 pub mod output;
+pub mod output_management;
+pub mod screenshot;
 pub mod shell;
+pub mod shell_handle;
 pub mod surface;
 pub mod window;
 pub mod workspace;
 
 use self::commands::Command;
-use self::output::{OutputData, OutputLayoutHandler, OutputManagerHandler};
-use self::shell::XdgV6ShellManagerHandler;
+use self::decoration::XdgDecorationManager;
+use self::deferred::{schedule_deferred_actions, DeferredAction};
+use self::layer_shell::{Layer, LayerShellManagerHandler, LayerSurface};
+use self::output::{
+	default_output_change, find_output_in_direction, output_change_from_config, place_output, OutputChange, OutputData,
+	OutputLayoutHandler, OutputManagerHandler,
+};
+use self::output_management::{publish_output_states, OutputManagement, RequestedOutputState};
+use self::screenshot::{PendingScreenshot, ScreenshotTarget};
+use self::shell::{XWaylandManagerHandler, XdgShellManagerHandler, XdgV6ShellManagerHandler};
+use self::shell_handle::ShellHandle;
 use self::window::Window;
 use self::workspace::Workspace;
+use common::ipc::IpcEvent;
+use config::global::DecorationPolicy;
+use config::keybinding::{Binding, BindingMatch, DEFAULT_MODE};
+use config::mousebinding::MouseInput;
+use config::output::OutputConfig;
 use config::Config;
 use input::cursor::CursorHandler;
-use input::keyboard::XkbKeySet;
+use input::keyboard::{compile_keymap, XkbKeySet};
+use input::pointer::{resize_cursor_name, PointerGrab, PointerGrabKind};
 use input::seat::SeatHandler;
 use input::InputManagerHandler;
-use layout::LayoutDirection;
+use ipc::IpcServer;
+use layout::{LayoutDirection, LayoutSplit};
 use utils::graphics::texture_helper;
 
+/// Minimal distance, in pixels, from which the boundary between two windows can be grabbed with the pointer.
+const MIN_SPLIT_GRAB_TOLERANCE: f64 = 4.0;
+
 /*
 ..####....####...##...##..#####....####....####...######..######...####...#####..
 .##..##..##..##..###.###..##..##..##..##..##........##......##....##..##..##..##.
@@ -59,19 +92,65 @@ pub fn generate_default_compositor() -> WLRCompositor {
 	let layout = WLROutputLayout::create(Box::new(OutputLayoutHandler));
 
 	// ? WIP: Initialize the compositor structure
-	let mut compositor = WLRCompositorBuilder::new()
+	let comfy_kernel = ComfyKernel::new(layout, xcursor_manager, cursor);
+	let mut compositor_builder = WLRCompositorBuilder::new()
 		.gles2(true)
 		.data_device(true)
 		.server_decoration_manager(true)
 		.input_manager(Box::new(InputManagerHandler))
 		.output_manager(Box::new(OutputManagerHandler))
 		.xdg_shell_v6_manager(Box::new(XdgV6ShellManagerHandler))
-		.build_auto(ComfyKernel::new(layout, xcursor_manager, cursor));
+		.xdg_shell_manager(Box::new(XdgShellManagerHandler))
+		.layer_shell(Box::new(LayerShellManagerHandler));
+	if comfy_kernel.config.global.xwayland {
+		compositor_builder = compositor_builder.xwayland(Box::new(XWaylandManagerHandler));
+	}
+	let mut compositor = compositor_builder.build_auto(comfy_kernel);
 
-	// ? Use the server-side decoration mode to avoid client-side decoration
+	// ? The X11 applications spawned by the compositor connect to it's XWayland server
+	if let Some(ref xwayland) = compositor.xwayland {
+		let display = format!(":{}", xwayland.display());
+		info!("Started XWayland on display {}", display);
+		env::set_var("DISPLAY", display);
+	}
+
+	// ? The KDE protocol can't be negotiated per window, it only follows the default decoration policy
 	// * Note: `ServerDecorationMode::None` does not seem to work
+	let default_decoration_mode = {
+		let comfy_kernel: &mut ComfyKernel = (&mut compositor).into();
+		match comfy_kernel.config.global.decoration_policy {
+			DecorationPolicy::ForceServer => ServerDecorationMode::Server,
+			DecorationPolicy::PreferClient => ServerDecorationMode::Client,
+		}
+	};
 	if let Some(ref mut decoration_manager) = compositor.server_decoration_manager {
-		decoration_manager.set_default_mode(ServerDecorationMode::Server);
+		decoration_manager.set_default_mode(default_decoration_mode);
+	}
+
+	// ? Clients speaking the stable xdg-shell negotiate their decorations per window through xdg-decoration
+	{
+		let display = compositor.display;
+		let comfy_kernel: &mut ComfyKernel = (&mut compositor).into();
+		match XdgDecorationManager::create(display) {
+			Ok(decoration_manager) => comfy_kernel.decoration_manager = Some(decoration_manager),
+			Err(e) => error!("{}", e),
+		}
+	}
+
+	// ? Lets external tools capture the outputs, the built-in `screenshot` command reads back the frames on it's own
+	if let Err(e) = screenshot::create_screencopy_manager(compositor.display) {
+		error!("{}", e);
+	}
+
+	// ? Lets external tools (kanshi, wlr-randr, etc...) configure the outputs, their state is published once they are
+	// added to the layout
+	{
+		let display = compositor.display;
+		let comfy_kernel: &mut ComfyKernel = (&mut compositor).into();
+		match OutputManagement::create(display) {
+			Ok(output_management) => comfy_kernel.output_management = Some(output_management),
+			Err(e) => error!("{}", e),
+		}
 	}
 
 	// ? WIP: Initialize and add the seat structures to the kernel
@@ -85,6 +164,23 @@ pub fn generate_default_compositor() -> WLRCompositor {
 		comfy_kernel.seat_handle = Some(seat_handle);
 	}
 
+	// ? Lets the kernel defer the calls it can't make while the compositor is borrowed
+	{
+		let event_loop = compositor.event_loop;
+		let comfy_kernel: &mut ComfyKernel = (&mut compositor).into();
+		comfy_kernel.event_loop = event_loop;
+	}
+
+	// ? Starts listening for IPC clients inside the compositor's event loop
+	{
+		let event_loop = compositor.event_loop;
+		let comfy_kernel: &mut ComfyKernel = (&mut compositor).into();
+		match IpcServer::start(event_loop) {
+			Ok(ipc_server) => comfy_kernel.ipc_server = Some(ipc_server),
+			Err(e) => error!("Could not start the IPC server: {}", e),
+		}
+	}
+
 	compositor
 }
 
@@ -104,14 +200,50 @@ pub struct ComfyKernel {
 	pub output_layout_handle: WLROutputLayoutHandle,
 	pub active_output_name: String,
 	pub output_data_map: HashMap<String, OutputData>,
-	pub workspace_pool: Vec<Workspace>,
+	/// Outputs plugged in but disabled by their configuration, they are kept out of the layout.
+	pub disabled_output_handles: Vec<WLROutputHandle>,
+	/// Configurations of the outputs as they were last applied, the outputs whose section is removed are reset.
+	pub applied_output_configs: HashMap<String, OutputConfig>,
+	/// Workspaces of the outputs unplugged while no other output was left to display their windows, by output name.
+	pub parked_workspaces: HashMap<String, BTreeMap<u32, Workspace>>,
 	pub seat_handle: Option<WLRSeatHandle>,
 	pub config: Config,
 	pub currently_pressed_keys: XkbKeySet,
+	/// Mode of the keybindings in which the pressed keys are looked up.
+	pub binding_mode: String,
+	/// Keysets of the sequence being typed, with the time at which the last one was pressed.
+	pub pending_key_sequence: Option<(Vec<XkbKeySet>, Instant)>,
+	/// Command of the `on_release` binding of the pressed keys, run when they are released unless another key is
+	/// pressed first.
+	pub release_command: Option<(XkbKeySet, Command)>,
+	/// Press of the last key of an `on_release` binding kept from the clients, as `(time_msec, keycode)`. They get it
+	/// if another key is pressed while it's held, otherwise it's release is kept from them as well.
+	pub withheld_key_press: Option<(u32, u32)>,
 	pub cursor_direction: LayoutDirection,
-	pub wallpaper_texture: Option<Texture<'static>>,
+	/// Textures of the wallpapers used by the theme, by image path.
+	pub wallpaper_textures: HashMap<String, Texture<'static>>,
 	pub should_load_wallpaper: bool,
-	pub window_stack: Vec<WLRXdgV6ShellSurfaceHandle>,
+	/// Windows taken out of their workspace by `put_active_window_to_stack`, the last one is popped first.
+	pub window_stack: Vec<Window>,
+	pub ipc_server: Option<IpcServer>,
+	/// Interaction of the pointer currently captured by the compositor, if any.
+	pub pointer_grab: Option<PointerGrab>,
+	/// Buttons pressed for a mouse binding, their release is kept from the clients as well.
+	pub pressed_bound_buttons: Vec<u32>,
+	/// Pointer buttons currently held, whoever they were sent to, in the order they were pressed.
+	pub held_buttons: Vec<u32>,
+	/// X11 surfaces placed by their client above everything else. (Menus, tooltips, etc...)
+	pub unmanaged_windows: Vec<Window>,
+	/// Windows of the X11 surfaces hidden by their client (Example: minimized to the tray), until they are shown again
+	/// or destroyed.
+	pub unmapped_windows: Vec<Window>,
+	pub decoration_manager: Option<XdgDecorationManager>,
+	pub output_management: Option<OutputManagement>,
+	/// Screenshots captured from the next frame of their output, by output name.
+	pub pending_screenshots: HashMap<String, Vec<PendingScreenshot>>,
+	pub event_loop: *mut wl_event_loop,
+	/// Actions waiting for the event loop to be idle, see `DeferredAction`.
+	pub deferred_actions: Vec<DeferredAction>,
 }
 
 // TODO: handle main seat features like notifying keyboard/cursor events
@@ -128,17 +260,46 @@ impl ComfyKernel {
 			output_layout_handle: output_layout_handle,
 			active_output_name: String::from(""),
 			output_data_map: HashMap::<String, OutputData>::new(),
-			workspace_pool: vec![],
+			disabled_output_handles: Vec::new(),
+			applied_output_configs: HashMap::new(),
+			parked_workspaces: HashMap::new(),
 			seat_handle: None,
 			config: Config::load(),
 			currently_pressed_keys: XkbKeySet::new(),
+			binding_mode: DEFAULT_MODE.to_string(),
+			pending_key_sequence: None,
+			release_command: None,
+			withheld_key_press: None,
 			cursor_direction: LayoutDirection::Right,
-			wallpaper_texture: None,
+			wallpaper_textures: HashMap::new(),
 			should_load_wallpaper: true,
 			window_stack: Vec::new(),
+			ipc_server: None,
+			pointer_grab: None,
+			pressed_bound_buttons: Vec::new(),
+			held_buttons: Vec::new(),
+			unmanaged_windows: Vec::new(),
+			unmapped_windows: Vec::new(),
+			decoration_manager: None,
+			output_management: None,
+			pending_screenshots: HashMap::new(),
+			event_loop: ptr::null_mut(),
+			deferred_actions: Vec::new(),
 		}
 	}
 
+	/// Runs the action once the event loop is idle, outside of the event being handled. The same action already
+	/// waiting is moved after the others instead of running twice.
+	pub fn defer(&mut self, deferred_action: DeferredAction) {
+		if self.deferred_actions.is_empty() {
+			schedule_deferred_actions(self.event_loop);
+		}
+		self
+			.deferred_actions
+			.retain(|waiting_action| *waiting_action != deferred_action);
+		self.deferred_actions.push(deferred_action);
+	}
+
 	/// Schedule a frame of the output which the name match with the provided string
 	#[wlroots_dehandle(layout, output)]
 	pub fn schedule_frame_for_output(&self, output_name: &str) {
@@ -155,32 +316,460 @@ impl ComfyKernel {
 		});
 	}
 
+	/// Applies the configuration of each output again (Example: after a config reload). Setting a mode emits events
+	/// handled while the compositor is borrowed, so the configurations are applied once the event loop is idle.
+	pub fn apply_output_configs(&mut self) {
+		self.defer(DeferredAction::ApplyOutputConfigs);
+	}
+
+	/// Enables or disables the outputs and moves them in the layout as their configuration requests, then returns the
+	/// mode, scale and transform to apply on each of them. The outputs whose section was removed from the config get
+	/// their default mode back and are placed next to the other outputs.
+	#[wlroots_dehandle(output_layout, output, disabled_output)]
+	pub fn prepare_output_changes(&mut self) -> Vec<OutputChange> {
+		let output_configs = self.config.global.outputs.clone();
+		let mut output_changes = Vec::new();
+		let mut disabled_output_names = Vec::new();
+		{
+			let output_layout_handle = self.output_layout_handle.clone();
+			use output_layout_handle as output_layout;
+			for (output_handle, _) in output_layout.outputs() {
+				use output_handle as output;
+				let output_name = output.name();
+				match output_configs.get(&output_name) {
+					Some(output_config) if !output_config.enabled => {
+						output_layout.remove(output);
+						output.enable(false);
+						disabled_output_names.push(output_name);
+						self.disabled_output_handles.push(output_handle.clone());
+					}
+					Some(output_config) => {
+						output_changes.push(output_change_from_config(output, output_config));
+						if let Some((x, y)) = output_config.position {
+							output_layout.move_output(output, Origin::new(x, y));
+						}
+					}
+					None if self.applied_output_configs.contains_key(&output_name) => {
+						output_changes.push(default_output_change(output));
+						output_layout.remove(output);
+						output_layout.add_auto(output);
+					}
+					None => {}
+				}
+			}
+
+			// ? The outputs disabled before are enabled again, unless their configuration still disables them
+			let disabled_output_handles: Vec<WLROutputHandle> = self.disabled_output_handles.drain(..).collect();
+			for disabled_output_handle in disabled_output_handles {
+				use disabled_output_handle as disabled_output;
+				let output_name = disabled_output.name();
+				let output_config_option = output_configs.get(&output_name);
+				if disabled_output_names.contains(&output_name)
+					|| output_config_option
+						.map(|output_config| !output_config.enabled)
+						.unwrap_or(false)
+				{
+					self.disabled_output_handles.push(disabled_output_handle.clone());
+				} else {
+					disabled_output.enable(true);
+					output_changes.push(match output_config_option {
+						Some(output_config) => output_change_from_config(disabled_output, output_config),
+						None => default_output_change(disabled_output),
+					});
+					place_output(output_layout, disabled_output, output_config_option);
+				}
+			}
+		}
+		for output_name in disabled_output_names {
+			self.remove_output_data(&output_name);
+		}
+		self.applied_output_configs = output_configs;
+		output_changes
+	}
+
+	/// Enables or disables the outputs and moves them in the layout as requested by a client of wlr-output-management,
+	/// then returns the mode, scale and transform to apply on each of them.
+	#[wlroots_dehandle(output_layout, output, disabled_output)]
+	pub fn prepare_requested_output_changes(
+		&mut self,
+		requested_output_states: &[RequestedOutputState],
+	) -> Vec<OutputChange> {
+		let mut output_changes = Vec::new();
+		let mut disabled_output_names = Vec::new();
+		{
+			let output_layout_handle = self.output_layout_handle.clone();
+			use output_layout_handle as output_layout;
+			for (output_handle, _) in output_layout.outputs() {
+				use output_handle as output;
+				let output_ptr = unsafe { output.as_ptr() };
+				let requested_output_state = match requested_output_states
+					.iter()
+					.find(|requested_output_state| requested_output_state.output_change.output == output_ptr)
+				{
+					Some(requested_output_state) => requested_output_state,
+					None => continue,
+				};
+				if requested_output_state.enabled {
+					let (x, y) = requested_output_state.position;
+					output_layout.move_output(output, Origin::new(x, y));
+					output_changes.push(requested_output_state.output_change.clone());
+				} else {
+					output_layout.remove(output);
+					output.enable(false);
+					disabled_output_names.push(output.name());
+					self.disabled_output_handles.push(output_handle.clone());
+				}
+			}
+
+			let disabled_output_handles: Vec<WLROutputHandle> = self.disabled_output_handles.drain(..).collect();
+			for disabled_output_handle in disabled_output_handles {
+				use disabled_output_handle as disabled_output;
+				let output_ptr = unsafe { disabled_output.as_ptr() };
+				match requested_output_states
+					.iter()
+					.find(|requested_output_state| requested_output_state.output_change.output == output_ptr)
+				{
+					Some(requested_output_state) if requested_output_state.enabled => {
+						let (x, y) = requested_output_state.position;
+						disabled_output.enable(true);
+						output_layout.add(disabled_output, Origin::new(x, y));
+						output_changes.push(requested_output_state.output_change.clone());
+					}
+					_ => self.disabled_output_handles.push(disabled_output_handle.clone()),
+				}
+			}
+		}
+		for output_name in disabled_output_names {
+			self.remove_output_data(&output_name);
+		}
+		output_changes
+	}
+
+	/// Updates the areas of the outputs once their changes were applied, then publishes their new state. The cursor
+	/// theme is loaded for their new scales.
+	pub fn finish_output_changes(&mut self, output_changes: &[OutputChange]) {
+		for scale in output_changes.iter().filter_map(|output_change| output_change.scale) {
+			self.xcursor_manager.load(scale);
+		}
+		self.refresh_output_areas();
+		publish_output_states(self);
+	}
+
+	/// Updates the area of each output of the layout from it's position and resolution, then rebalances it's
+	/// workspaces. The outputs without data yet (Example: an output enabled again) get a new one.
+	#[wlroots_dehandle(output_layout, output)]
+	pub fn refresh_output_areas(&mut self) {
+		let mut added_output_names = Vec::new();
+		{
+			let output_layout_handle = self.output_layout_handle.clone();
+			use output_layout_handle as output_layout;
+			for (output_handle, _) in output_layout.outputs() {
+				use output_handle as output;
+				let (x, y) = output.layout_space_pos();
+				let (width, height) = output.effective_resolution();
+				let area = Area::new(Origin::new(x, y), Size::new(width, height));
+				match self.output_data_map.get_mut(&output.name()) {
+					Some(output_data) => output_data.update_area_and_rebalance(area),
+					None => {
+						self.output_data_map.insert(output.name(), OutputData::new(area));
+						added_output_names.push(output.name());
+					}
+				}
+				output.schedule_frame();
+			}
+		}
+		for output_name in added_output_names {
+			self.restore_parked_workspaces(&output_name);
+			self.emit_event(IpcEvent::OutputAdded { output: output_name });
+		}
+	}
+
+	/// Removes the data of an output which left the layout. The windows of it's workspaces are moved to the workspaces
+	/// with the same numbers on the active output, or parked until an output is added if no other output is left.
+	pub fn remove_output_data(&mut self, output_name: &str) {
+		let removed_output_data_option = self.output_data_map.remove(output_name);
+		if let Some(pending_screenshots) = self.pending_screenshots.remove(output_name) {
+			warn!(
+				"Dropped {} screenshot(s) of {}, the output was removed before it's next frame",
+				pending_screenshots.len(),
+				output_name
+			);
+		}
+		debug!(
+			"Removed OutputData from data_map! Nb of total entries: {}",
+			self.output_data_map.len()
+		);
+
+		// ? The active output can't be the removed one, any remaining output takes it's place
+		let active_output_was_removed = !self.output_data_map.contains_key(&self.active_output_name);
+		if active_output_was_removed {
+			self.active_output_name = self.output_data_map.keys().min().cloned().unwrap_or_default();
+		}
+
+		if let Some(removed_output_data) = removed_output_data_option {
+			let workspaces: BTreeMap<u32, Workspace> = removed_output_data
+				.workspaces
+				.into_iter()
+				.filter(|(_, workspace)| !workspace.is_empty())
+				.collect();
+			if !workspaces.is_empty() {
+				if self.output_data_map.is_empty() {
+					info!("No output left, parking the windows of {}", output_name);
+					self.parked_workspaces.insert(output_name.to_string(), workspaces);
+				} else {
+					info!("Moving the windows of {} to {}", output_name, self.active_output_name);
+					let active_output_name = self.active_output_name.clone();
+					self.move_workspaces_to_output(workspaces, &active_output_name);
+				}
+			}
+		}
+
+		if active_output_was_removed {
+			self.focus_active_workspace();
+		}
+		self.emit_event(IpcEvent::OutputRemoved {
+			output: output_name.to_string(),
+		});
+	}
+
+	/// Gives the parked workspaces of an output back once it's added to the layout again. If it's the only output, it
+	/// also takes the workspaces parked by the other outputs so no window stays hidden.
+	pub fn restore_parked_workspaces(&mut self, output_name: &str) {
+		let parked_output_names: Vec<String> = if self.output_data_map.len() == 1 {
+			self.parked_workspaces.keys().cloned().collect()
+		} else {
+			self
+				.parked_workspaces
+				.keys()
+				.filter(|parked_output_name| *parked_output_name == output_name)
+				.cloned()
+				.collect()
+		};
+		if parked_output_names.is_empty() {
+			return;
+		}
+		for parked_output_name in parked_output_names {
+			if let Some(workspaces) = self.parked_workspaces.remove(&parked_output_name) {
+				info!("Restoring the windows of {} on {}", parked_output_name, output_name);
+				self.move_workspaces_to_output(workspaces, output_name);
+			}
+		}
+		if output_name == self.active_output_name {
+			self.focus_active_workspace();
+		}
+		self.schedule_frame_for_output(output_name);
+	}
+
+	/// Moves the windows of the provided workspaces to the workspaces with the same numbers on an output.
+	fn move_workspaces_to_output(&mut self, workspaces: BTreeMap<u32, Workspace>, output_name: &str) {
+		let direction = self.cursor_direction.clone();
+		match self.output_data_map.get_mut(output_name) {
+			Some(output_data) => {
+				for (workspace_number, workspace) in workspaces {
+					output_data
+						.get_or_create_workspace(workspace_number)
+						.take_windows_from(workspace, &direction);
+				}
+			}
+			None => error!("Failed to get output data for output: {}", output_name),
+		}
+	}
+
+	/// Gives the keyboard focus to the active window of the workspace displayed on the active output.
+	fn focus_active_workspace(&mut self) {
+		let active_shell_option = self
+			.output_data_map
+			.get(&self.active_output_name)
+			.and_then(|output_data| output_data.active_workspace().get_active_shell_handle());
+		match active_shell_option {
+			Some(active_shell) => self.apply_keyboard_focus(&active_shell),
+			None => self.clear_keyboard_focus(),
+		}
+	}
+
+	/// Stops tracking a disabled output. (Example: the output was unplugged)
+	pub fn forget_disabled_output(&mut self, output_name: &str) {
+		self.disabled_output_handles.retain(|output_handle| {
+			output_handle
+				.run(|output| output.name() != output_name)
+				.unwrap_or(false)
+		});
+	}
+
+	/// Returns the workspace displayed on the active output if any.
+	pub fn get_active_workspace_mut(&mut self) -> Option<&mut Workspace> {
+		self
+			.output_data_map
+			.get_mut(&self.active_output_name)
+			.map(|output_data| output_data.active_workspace_mut())
+	}
+
 	/// Sets or unsets the fullscreen active window.
-	pub fn toggle_active_window_fullscreen(&mut self) {
-		if let Some(OutputData { workspace, .. }) = self.output_data_map.get_mut(&self.active_output_name) {
+	pub fn toggle_active_window_fullscreen(&mut self) -> Result<(), String> {
+		let active_output_name = self.active_output_name.clone();
+		let toggled_window = {
+			let workspace = self
+				.get_active_workspace_mut()
+				.ok_or_else(|| format!("Failed to get output data for active output: {}", active_output_name))?;
+			// ? Only the windows of the layout can be displayed in fullscreen
+			if workspace.floating_window_is_active {
+				return Err("A floating window can't be displayed in fullscreen".to_string());
+			}
 			workspace.window_layout.toggle_active_window_fullscreen();
+			workspace
+				.window_layout
+				.get_active_window()
+				.ok_or_else(|| "No active window found".to_string())?
+		};
+		self.emit_event(IpcEvent::FullscreenToggled {
+			window: toggled_window.get_info(),
+		});
+		Ok(())
+	}
+
+	/// Sends the event to the IPC clients subscribed to it.
+	pub fn emit_event(&mut self, event: IpcEvent) {
+		if let Some(ref mut ipc_server) = self.ipc_server {
+			ipc_server.broadcast_event(&event);
 		}
 	}
 
+	/// Returns the window bound to the provided shell handle, from any workspace of any output or parked, from the
+	/// window stack, or from the unmanaged windows.
+	pub fn find_window(&self, shell_handle: &ShellHandle) -> Option<Window> {
+		self
+			.output_data_map
+			.values()
+			.flat_map(|output_data| output_data.workspaces.values())
+			.chain(
+				self
+					.parked_workspaces
+					.values()
+					.flat_map(|workspaces| workspaces.values()),
+			)
+			.filter_map(|workspace| workspace.get_window_from_shell_handle(shell_handle))
+			.chain(self.window_stack.iter())
+			.chain(self.unmanaged_windows.iter())
+			.find(|window| window.shell_handle == *shell_handle)
+			.cloned()
+	}
+
 	/// Move the 'cursor' in the layout in a given direction.
-	pub fn move_cursor_in_active_output(&mut self, direction: LayoutDirection) {
+	/// At the edge of the layout, the focus goes to the neighbouring output in that direction if there is one.
+	pub fn move_cursor_in_active_output(&mut self, direction: LayoutDirection) -> Result<(), String> {
 		let mut shell_handle_option = None;
-		if let Some(OutputData { workspace, .. }) = self.output_data_map.get_mut(&self.active_output_name) {
+		let mut is_at_edge_of_layout = false;
+		if let Some(workspace) = self.get_active_workspace_mut() {
 			if !workspace.window_layout.has_fullscreen_window() {
 				shell_handle_option = workspace
 					.window_layout
 					.get_shell_handle_relative_to_active_node(&direction);
+				is_at_edge_of_layout = shell_handle_option.is_none();
 			}
 		} else {
-			error!(
+			return Err(format!(
 				"Failed to get output data for active output: {}",
 				self.active_output_name
-			);
+			));
 		}
 		if let Some(shell_handle) = shell_handle_option {
 			self.apply_keyboard_focus(&shell_handle);
 			self.schedule_frame_for_output(&self.active_output_name);
+		} else if is_at_edge_of_layout {
+			if let Some(output_name) = self.find_output_next_to_active_one(&direction) {
+				return self.focus_output(&output_name);
+			}
 		}
+		Ok(())
+	}
+
+	/// Makes the output under the cursor the active one, so new windows are opened where the user is looking. The
+	/// keyboard focus follows, the commands acting on the active window would otherwise act on a window of the output
+	/// under the cursor instead of the one receiving the keys. Nothing changes during a grab, the grabbed window keeps
+	/// the focus.
+	pub fn update_active_output_under_cursor(&mut self) {
+		if self.pointer_grab.is_some() {
+			return;
+		}
+		let (x, y) = self.get_cursor_coordinates();
+		let output_name_option = self
+			.output_data_map
+			.iter()
+			.find(|(_, output_data)| output_data.area.contains_point(x, y))
+			.map(|(output_name, _)| output_name.clone());
+		if let Some(output_name) = output_name_option {
+			if output_name != self.active_output_name {
+				debug!("The cursor entered the output {}", output_name);
+				if let Err(e) = self.focus_output(&output_name) {
+					error!("{}", e);
+				}
+			}
+		}
+	}
+
+	/// Returns the name of the closest output next to the active one in the provided direction, if any.
+	pub fn find_output_next_to_active_one(&self, direction: &LayoutDirection) -> Option<String> {
+		let active_output_area = self.output_data_map.get(&self.active_output_name)?.area;
+		find_output_in_direction(
+			self
+				.output_data_map
+				.iter()
+				.filter(|(output_name, _)| **output_name != self.active_output_name)
+				.map(|(output_name, output_data)| (output_name, &output_data.area)),
+			&active_output_area,
+			direction,
+		)
+	}
+
+	/// Makes the provided output the active one, then gives the focus to the active window of it's displayed workspace.
+	pub fn focus_output(&mut self, output_name: &str) -> Result<(), String> {
+		if !self.output_data_map.contains_key(output_name) {
+			return Err(format!("Failed to get output data for output: {}", output_name));
+		}
+		let previous_output_name = mem::replace(&mut self.active_output_name, output_name.to_string());
+		self.focus_active_workspace();
+		self.schedule_frame_for_output(&previous_output_name);
+		self.schedule_frame_for_output(output_name);
+		Ok(())
+	}
+
+	/// Moves the active window to the displayed workspace of another output, which becomes the active one.
+	/// A tiled window is added next to the active window of that workspace, in the provided direction.
+	pub fn move_active_window_to_output(&mut self, output_name: &str, direction: &LayoutDirection) -> Result<(), String> {
+		if output_name == self.active_output_name {
+			return Ok(());
+		}
+		if !self.output_data_map.contains_key(output_name) {
+			return Err(format!("Failed to get output data for output: {}", output_name));
+		}
+		let active_output_name = self.active_output_name.clone();
+		let (window, window_is_floating) = {
+			let workspace = self
+				.get_active_workspace_mut()
+				.ok_or_else(|| format!("Failed to get output data for active output: {}", active_output_name))?;
+			let active_shell_handle = workspace
+				.get_active_shell_handle()
+				.ok_or_else(|| "No active window found".to_string())?;
+			let window_is_floating = workspace.floating_window_is_active;
+			(
+				workspace.remove_window_from_shell_handle(&active_shell_handle)?,
+				window_is_floating,
+			)
+		};
+
+		let shell_handle = window.shell_handle.clone();
+		let previous_output_name = mem::replace(&mut self.active_output_name, output_name.to_string());
+		if let Some(workspace) = self.get_active_workspace_mut() {
+			if window_is_floating {
+				workspace.add_floating_window(window);
+			} else if let Err(e) = workspace.window_layout.add_window(window, direction, true, true) {
+				error!("{}", e);
+			}
+		}
+		self.apply_keyboard_focus(&shell_handle);
+		self.schedule_frame_for_output(&previous_output_name);
+		self.schedule_frame_for_output(output_name);
+		Ok(())
 	}
 
 	/// Sets the direction of the 'cursor' of the layout.
@@ -189,35 +778,254 @@ impl ComfyKernel {
 	}
 
 	pub fn get_active_window(&mut self) -> Option<Window> {
-		if let Some(OutputData { workspace, .. }) = self.output_data_map.get_mut(&self.active_output_name) {
-			return workspace.window_layout.get_active_window();
+		if let Some(workspace) = self.get_active_workspace_mut() {
+			return workspace.get_active_window();
 		}
 		None
 	}
 
 	/// Moves the active window in the active layout in a given direction.
-	pub fn move_active_window(&mut self, direction: LayoutDirection) {
-		if let Some(OutputData { workspace, .. }) = self.output_data_map.get_mut(&self.active_output_name) {
-			workspace.window_layout.move_active_window(&direction);
+	/// At the edge of the layout, the window crosses to the neighbouring output in that direction if there is one.
+	pub fn move_active_window(&mut self, direction: LayoutDirection) -> Result<(), String> {
+		let neighbouring_output_option = self.find_output_next_to_active_one(&direction);
+		let mut output_to_cross_to_option = None;
+		if let Some(workspace) = self.get_active_workspace_mut() {
+			// ? The active window of the layout is hidden behind the focused floating window
+			if !workspace.floating_window_is_active {
+				let is_at_edge_of_layout = workspace.window_layout.get_active_shell_handle().is_some()
+					&& workspace
+						.window_layout
+						.get_shell_handle_relative_to_active_node(&direction)
+						.is_none();
+				match neighbouring_output_option {
+					Some(output_name) if is_at_edge_of_layout => output_to_cross_to_option = Some(output_name),
+					_ => workspace.window_layout.move_active_window(&direction),
+				}
+			}
 		} else {
-			error!(
+			return Err(format!(
 				"Failed to get output data for active output: {}",
 				self.active_output_name
-			);
+			));
+		}
+		match output_to_cross_to_option {
+			// ? The window enters the other output from the side it comes from
+			Some(output_name) => self.move_active_window_to_output(&output_name, &direction.get_opposite()),
+			None => {
+				self.schedule_frame_for_output(&self.active_output_name);
+				Ok(())
+			}
+		}
+	}
+
+	/// Grows (positive amount) or shrinks (negative amount) the active window of the active layout.
+	pub fn resize_active_window(&mut self, direction: LayoutDirection, amount: f32) -> Result<(), String> {
+		let active_output_name = self.active_output_name.clone();
+		{
+			let workspace = self
+				.get_active_workspace_mut()
+				.ok_or_else(|| format!("Failed to get output data for active output: {}", active_output_name))?;
+			if workspace.floating_window_is_active {
+				return Err("A floating window can't be resized by the layout".to_string());
+			}
+			if workspace.window_layout.get_active_shell_handle().is_none() {
+				return Err("No active window to resize".to_string());
+			}
+			workspace.window_layout.resize_active_window(&direction, amount);
+		}
+		self.schedule_frame_for_output(&self.active_output_name);
+		Ok(())
+	}
+
+	/// Returns the name of the output, the number of it's displayed workspace and the split of it's layout found at the
+	/// provided coordinates, if any. Splits can be grabbed a few pixels around their boundary.
+	pub fn find_split_at(&self, x: f64, y: f64) -> Option<(String, u32, LayoutSplit)> {
+		let tolerance = f64::from(self.config.theme.border_size).max(MIN_SPLIT_GRAB_TOLERANCE);
+		for (output_name, output_data) in self.output_data_map.iter() {
+			if output_data.area.contains_point(x, y) {
+				let workspace = output_data.active_workspace();
+				return workspace
+					.window_layout
+					.find_split_at(x, y, tolerance)
+					.map(|split| (output_name.clone(), workspace.number, split));
+			}
+		}
+		None
+	}
+
+	/// Returns the split under the cursor, see `find_split_at`. Without the modkey, a split is only grabbed from the
+	/// borders between the windows, so the edges of their content (scrollbars, resize edges of client side decorations,
+	/// etc...) still receive the clicks.
+	pub fn find_split_under_cursor(&mut self) -> Option<(String, u32, LayoutSplit)> {
+		let (cursor_x, cursor_y) = self.get_cursor_coordinates();
+		let split_option = self.find_split_at(cursor_x, cursor_y);
+		if split_option.is_some() && !self.modkey_is_pressed() && self.get_surface_at(cursor_x, cursor_y).is_some() {
+			return None;
+		}
+		split_option
+	}
+
+	/// Moves the split of the provided workspace to the cursor.
+	pub fn move_split_to_cursor(&mut self, output_name: &str, workspace_number: u32, split: &LayoutSplit) {
+		let (cursor_x, cursor_y) = self.get_cursor_coordinates();
+		if let Some(output_data) = self.output_data_map.get_mut(output_name) {
+			if let Some(workspace) = output_data.workspaces.get_mut(&workspace_number) {
+				workspace.window_layout.move_split(split, cursor_x, cursor_y);
+			}
+		}
+		self.schedule_frame_for_output(output_name);
+	}
+
+	/// Sets the image of the cursor from the xcursor theme. (Example: `left_ptr`)
+	#[wlroots_dehandle(cursor)]
+	pub fn set_cursor_image(&mut self, image_name: &str) {
+		let cursor_handle = &self.cursor_handle;
+		use cursor_handle as cursor;
+		self.xcursor_manager.set_cursor_image(image_name.to_string(), cursor);
+	}
+
+	/// Returns `true` if one of the keys configured as the modkey is currently pressed.
+	pub fn modkey_is_pressed(&self) -> bool {
+		self.config.keybindings.modkey.iter().any(|modkey_set| {
+			modkey_set
+				.keysyms_set
+				.is_subset(&self.currently_pressed_keys.keysyms_set)
+		})
+	}
+
+	/// Returns the top floating window of the displayed workspaces under the cursor.
+	pub fn find_floating_window_under_cursor(&self) -> Option<Window> {
+		let (cursor_x, cursor_y) = self.get_cursor_coordinates();
+		self.get_floating_window_at(cursor_x, cursor_y)
+	}
+
+	/// Returns a clone of the floating window bound to the provided shell handle, from any workspace.
+	fn find_floating_window(&self, shell_handle: &ShellHandle) -> Option<Window> {
+		self
+			.output_data_map
+			.values()
+			.flat_map(|output_data| output_data.workspaces.values())
+			.filter_map(|workspace| workspace.get_floating_window(shell_handle))
+			.next()
+			.cloned()
+	}
+
+	/// Starts moving the floating window bound to the provided shell handle with the cursor, or resizing it from the
+	/// provided edges, until the provided button is released. Returns false if the window isn't floating, tiled windows
+	/// can't be moved freely.
+	pub fn start_floating_window_grab(
+		&mut self,
+		shell_handle: &ShellHandle,
+		resize_edges_option: Option<u32>,
+		button: u32,
+		is_requested_by_client: bool,
+	) -> bool {
+		let window = match self.find_floating_window(shell_handle) {
+			Some(window) => window,
+			None => return false,
+		};
+		let cursor_origin = self.get_cursor_coordinates();
+		let kind = match resize_edges_option {
+			Some(edges) => {
+				self.set_cursor_image(resize_cursor_name(edges));
+				PointerGrabKind::ResizeFloatingWindow {
+					shell_handle: shell_handle.clone(),
+					cursor_origin,
+					initial_area: window.area.clone(),
+					edges,
+				}
+			}
+			None => {
+				self.set_cursor_image("fleur");
+				PointerGrabKind::MoveFloatingWindow {
+					shell_handle: shell_handle.clone(),
+					cursor_origin,
+					initial_area: window.area.clone(),
+				}
+			}
+		};
+		self.pointer_grab = Some(PointerGrab {
+			kind,
+			button,
+			is_requested_by_client,
+		});
+		true
+	}
+
+	/// Sets the area of the floating window bound to the provided shell handle. The size of the shell is only updated
+	/// if `should_resize` is true, moving a window doesn't need to reconfigure the client.
+	/// Returns false if no such floating window exists.
+	pub fn set_floating_window_area(&mut self, shell_handle: &ShellHandle, area: Area, should_resize: bool) -> bool {
+		let mut output_name_option = None;
+		for (output_name, output_data) in self.output_data_map.iter_mut() {
+			for workspace in output_data.workspaces.values_mut() {
+				if let Some(window) = workspace.get_floating_window_mut(shell_handle) {
+					if should_resize {
+						window.resize(area);
+					} else {
+						window.area = area;
+					}
+					output_name_option = Some(output_name.clone());
+					break;
+				}
+			}
+			if output_name_option.is_some() {
+				break;
+			}
+		}
+		match output_name_option {
+			Some(output_name) => {
+				self.schedule_frame_for_output(&output_name);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Stops the current grab of the pointer and restores the default cursor image.
+	pub fn end_pointer_grab(&mut self) {
+		self.pointer_grab = None;
+		self.set_cursor_image("left_ptr");
+	}
+
+	/// Moves the active window between the layout and the floating layer of the active workspace.
+	pub fn toggle_active_window_floating(&mut self) -> Result<(), String> {
+		let current_cursor_direction = self.cursor_direction.clone();
+		let active_output_name = self.active_output_name.clone();
+		let active_shell_option = {
+			let workspace = self
+				.get_active_workspace_mut()
+				.ok_or_else(|| format!("Failed to get output data for active output: {}", active_output_name))?;
+			workspace.toggle_active_window_floating(&current_cursor_direction)?;
+			workspace.get_active_shell_handle()
+		};
+		if let Some(active_shell) = active_shell_option {
+			self.apply_keyboard_focus(&active_shell);
 		}
 		self.schedule_frame_for_output(&self.active_output_name);
+		Ok(())
 	}
 
 	/// Add the provided shell handle as a new window inside the active workspace
-	pub fn add_window_to_active_workspace(&mut self, shell_handle: WLRXdgV6ShellSurfaceHandle) {
+	pub fn add_window_to_active_workspace(&mut self, shell_handle: ShellHandle) {
 		let current_cursor_direction = self.cursor_direction.clone();
 		let mut active_shell_option = None;
-		if let Some(OutputData { workspace, .. }) = self.output_data_map.get_mut(&self.active_output_name) {
+		let border_size = self.config.theme.border_size;
+		let animations = self.config.theme.animations.clone();
+		// ? The decorations may have been negotiated before the window was mapped
+		let client_side_decorated_surface_option = match self.decoration_manager {
+			Some(ref decoration_manager) if decoration_manager.has_client_side_decorations(&shell_handle) => {
+				shell_handle.xdg_surface_ptr()
+			}
+			_ => None,
+		};
+		if let Some(workspace) = self.get_active_workspace_mut() {
 			// TODO: Handle manual direction change for insertion
 			active_shell_option = match workspace.window_layout.add_shell_handle(
 				shell_handle,
 				&current_cursor_direction,
-				self.config.theme.border_size,
+				border_size,
+				&animations,
 				true,
 				true,
 			) {
@@ -234,49 +1042,369 @@ impl ComfyKernel {
 			);
 		}
 
+		if let Some(xdg_surface) = client_side_decorated_surface_option {
+			self.set_client_side_decorations(xdg_surface, true);
+		}
 		if let Some(active_shell) = active_shell_option {
 			self.apply_keyboard_focus(&active_shell);
 			self.schedule_frame_for_output(&self.active_output_name);
 		}
 	}
 
-	pub fn load_wallpaper(&mut self, gles2: &mut GenericRenderer) {
-		if let Some(wallpaper_path) = &self.config.theme.wallpaper_path {
-			self.wallpaper_texture = match texture_helper::load_texture(gles2, &Path::new(wallpaper_path)) {
-				Ok(wallpaper_texture) => Some(wallpaper_texture),
-				Err(e) => {
-					error!("{}", e);
-					None
+	/// Sets whether the window bound to the provided xdg surface draws it's own decorations, then applies it's new
+	/// geometry since the borders of the compositor are removed or added back.
+	pub fn set_client_side_decorations(&mut self, xdg_surface: *mut wlr_xdg_surface, has_client_side_decorations: bool) {
+		let mut updated_output_names = Vec::new();
+		for (output_name, output_data) in self.output_data_map.iter_mut() {
+			for workspace in output_data.workspaces.values_mut() {
+				let mut update_window = |window: &mut Window| {
+					if window.shell_handle.xdg_surface_ptr() == Some(xdg_surface)
+						&& window.has_client_side_decorations != has_client_side_decorations
+					{
+						window.has_client_side_decorations = has_client_side_decorations;
+						window.apply_resize();
+						updated_output_names.push(output_name.clone());
+					}
+				};
+				workspace.window_layout.for_each_window(&mut update_window);
+				workspace.floating_windows.iter_mut().for_each(update_window);
+			}
+		}
+		for output_name in updated_output_names {
+			self.schedule_frame_for_output(&output_name);
+		}
+	}
+
+	/// Gives the animations of the theme to every managed window (Example: after a config reload).
+	pub fn apply_theme_animations(&mut self) {
+		let animations = &self.config.theme.animations;
+		let workspaces = self
+			.output_data_map
+			.values_mut()
+			.flat_map(|output_data| output_data.workspaces.values_mut())
+			.chain(
+				self
+					.parked_workspaces
+					.values_mut()
+					.flat_map(|workspaces| workspaces.values_mut()),
+			);
+		for workspace in workspaces {
+			let mut update_window = |window: &mut Window| window.animations = animations.clone();
+			workspace.window_layout.for_each_window(&mut update_window);
+			workspace.floating_windows.iter_mut().for_each(update_window);
+		}
+	}
+
+	/// Queues a screenshot of the provided target, captured from the next frame of the output displaying it.
+	/// Only a region needs an area, the other targets are found from the active output.
+	pub fn request_screenshot(
+		&mut self,
+		target: &ScreenshotTarget,
+		region_option: Option<Area>,
+		path: PathBuf,
+	) -> Result<(), String> {
+		let (output_name, area) = match target {
+			ScreenshotTarget::Output => {
+				let output_data = self
+					.output_data_map
+					.get(&self.active_output_name)
+					.ok_or("No active output found")?;
+				(self.active_output_name.clone(), output_data.area)
+			}
+			ScreenshotTarget::Window => {
+				let window = self.get_active_window().ok_or("No active window found")?;
+				(self.active_output_name.clone(), window.area)
+			}
+			ScreenshotTarget::Region => {
+				let area = region_option.ok_or("No region was provided")?;
+				// ? A region spanning multiple outputs is clipped to the one containing it's origin
+				let output_name = self
+					.output_data_map
+					.iter()
+					.find(|(_, output_data)| {
+						output_data
+							.area
+							.contains_point(f64::from(area.origin.x), f64::from(area.origin.y))
+					}).map(|(output_name, _)| output_name.clone())
+					.ok_or("The region is outside of the outputs")?;
+				(output_name, area)
+			}
+		};
+		self.schedule_frame_for_output(&output_name);
+		self
+			.pending_screenshots
+			.entry(output_name)
+			.or_insert_with(Vec::new)
+			.push(PendingScreenshot { area, path });
+		Ok(())
+	}
+
+	/// Loads a texture for each wallpaper of the theme, the images used by several outputs are only loaded once.
+	/// The textures of the previous theme are dropped.
+	pub fn load_wallpapers(&mut self, gles2: &mut GenericRenderer) {
+		self.wallpaper_textures.clear();
+		for wallpaper_path in self.config.theme.all_wallpaper_paths() {
+			match texture_helper::load_texture(gles2, &Path::new(wallpaper_path)) {
+				Ok(wallpaper_texture) => {
+					self
+						.wallpaper_textures
+						.insert(wallpaper_path.clone(), wallpaper_texture);
 				}
+				Err(e) => error!("{}: {}", e, wallpaper_path),
 			}
 		}
 		self.should_load_wallpaper = false;
 	}
 
+	/// Adds an unmanaged X11 surface, displayed at the area requested by it's client.
+	pub fn add_unmanaged_window(&mut self, shell_handle: ShellHandle, area: Area) {
+		self.unmanaged_windows.push(Window::new(shell_handle, area, 0));
+	}
+
+	/// Updates the area of the unmanaged window bound to the provided shell handle.
+	/// Returns false if the shell handle isn't bound to an unmanaged window.
+	pub fn move_unmanaged_window(&mut self, shell_handle: &ShellHandle, area: Area) -> bool {
+		match self
+			.unmanaged_windows
+			.iter_mut()
+			.find(|window| window.shell_handle == *shell_handle)
+		{
+			Some(window) => {
+				window.area = area;
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Removes the unmanaged window bound to the provided shell handle if any.
+	pub fn remove_unmanaged_window(&mut self, shell_handle: &ShellHandle) {
+		self
+			.unmanaged_windows
+			.retain(|window| window.shell_handle != *shell_handle);
+	}
+
+	/// Adds the layer surface to the output requested by it's client, or to the active output, then arranges it.
+	#[wlroots_dehandle(output)]
+	pub fn add_layer_surface(&mut self, layer_surface_handle: WLRLayerSurfaceHandle) {
+		let requested_output_handle_option = layer_surface_handle
+			.run(|layer_surface| layer_surface.output())
+			.unwrap_or(None);
+		let output_name = match requested_output_handle_option {
+			Some(output_handle) => {
+				use output_handle as output;
+				output.name()
+			}
+			None => self.active_output_name.clone(),
+		};
+		let layer_surface = match LayerSurface::new(layer_surface_handle) {
+			Some(layer_surface) => layer_surface,
+			None => return,
+		};
+		if let Some(output_data) = self.output_data_map.get_mut(&output_name) {
+			output_data.layer_surfaces.push(layer_surface);
+			if output_data.arrange_layer_surfaces() {
+				output_data.rebalance_workspaces();
+			}
+		} else {
+			error!(
+				"Failed to get output data for the layer surface's output: {}",
+				output_name
+			);
+		}
+		self.schedule_frame_for_output(&output_name);
+	}
+
+	/// Removes the layer surface from it's output and gives back the space of it's exclusive zone to the workspaces.
+	pub fn remove_layer_surface(&mut self, layer_surface_handle: &WLRLayerSurfaceHandle) {
+		let mut removed_layer_surface_option = None;
+		let mut output_name_option = None;
+		for (output_name, output_data) in self.output_data_map.iter_mut() {
+			if let Some(index) = output_data
+				.layer_surfaces
+				.iter()
+				.position(|layer_surface| layer_surface.layer_surface_handle == *layer_surface_handle)
+			{
+				removed_layer_surface_option = Some(output_data.layer_surfaces.remove(index));
+				if output_data.arrange_layer_surfaces() {
+					output_data.rebalance_workspaces();
+				}
+				output_name_option = Some(output_name.clone());
+				break;
+			}
+		}
+		if let Some(output_name) = output_name_option {
+			self.schedule_frame_for_output(&output_name);
+		}
+
+		// ? The keyboard goes back to the active window when a launcher or a lock screen goes away
+		if let Some(removed_layer_surface) = removed_layer_surface_option {
+			if removed_layer_surface.is_mapped && removed_layer_surface.state.keyboard_interactive {
+				self.focus_active_workspace();
+			}
+		}
+	}
+
+	/// Marks the layer surface as mapped or unmapped by it's client, then arranges it's output again since only the
+	/// mapped surfaces take space from the workspaces.
+	pub fn set_layer_surface_mapped(&mut self, layer_surface_handle: &WLRLayerSurfaceHandle, is_mapped: bool) {
+		let mut output_name_option = None;
+		let mut is_keyboard_interactive = false;
+		for (output_name, output_data) in self.output_data_map.iter_mut() {
+			match output_data
+				.layer_surfaces
+				.iter_mut()
+				.find(|layer_surface| layer_surface.layer_surface_handle == *layer_surface_handle)
+			{
+				Some(layer_surface) => {
+					layer_surface.is_mapped = is_mapped;
+					is_keyboard_interactive = layer_surface.state.keyboard_interactive;
+				}
+				None => continue,
+			}
+			if output_data.arrange_layer_surfaces() {
+				output_data.rebalance_workspaces();
+			}
+			output_name_option = Some(output_name.clone());
+			break;
+		}
+		if let Some(output_name) = output_name_option {
+			self.schedule_frame_for_output(&output_name);
+		}
+		if !is_mapped && is_keyboard_interactive {
+			self.focus_active_workspace();
+		}
+	}
+
+	/// Arranges the output of the layer surface bound to the provided surface again if it's client changed it's state.
+	pub fn refresh_layer_surface(&mut self, surface_handle: &WLRSurfaceHandle) {
+		let mut output_name_option = None;
+		for (output_name, output_data) in self.output_data_map.iter_mut() {
+			let mut state_changed = false;
+			for layer_surface in output_data.layer_surfaces.iter_mut() {
+				if layer_surface.surface().as_ref() == Some(surface_handle) && layer_surface.refresh_state() {
+					state_changed = true;
+				}
+			}
+			if state_changed {
+				if output_data.arrange_layer_surfaces() {
+					output_data.rebalance_workspaces();
+				}
+				output_name_option = Some(output_name.clone());
+				break;
+			}
+		}
+		if let Some(output_name) = output_name_option {
+			self.schedule_frame_for_output(&output_name);
+		}
+	}
+
+	/// Gives the keyboard focus to the layer surface if it asks for it and is displayed above the windows.
+	#[wlroots_dehandle(seat, keyboard, surface)]
+	pub fn focus_layer_surface_if_interactive(&mut self, layer_surface_handle: &WLRLayerSurfaceHandle) {
+		let surface_handle_option = self
+			.output_data_map
+			.values()
+			.flat_map(|output_data| output_data.layer_surfaces.iter())
+			.find(|layer_surface| layer_surface.layer_surface_handle == *layer_surface_handle)
+			.filter(|layer_surface| {
+				layer_surface.is_mapped && layer_surface.state.keyboard_interactive && layer_surface.is_above_windows()
+			})
+			.and_then(|layer_surface| layer_surface.surface());
+		if let (Some(surface_handle), Some(seat_handle), Some(keyboard_handle)) = (
+			surface_handle_option,
+			self.seat_handle.clone(),
+			self.keyboard_handle.clone(),
+		) {
+			use keyboard_handle as keyboard;
+			use seat_handle as seat;
+			use surface_handle as surface;
+			seat.keyboard_notify_enter(surface, &mut keyboard.keycodes(), &mut keyboard.get_modifier_masks());
+		}
+	}
+
+	/// Returns the surface displayed at the provided coordinates, from a layer surface or a window, and the coordinates
+	/// relative to it.
+	fn get_surface_at(&mut self, x: f64, y: f64) -> Option<(WLRSurfaceHandle, f64, f64)> {
+		if let Some(layer_surface_above) = self.get_layer_surface_at(x, y, true) {
+			return Some(layer_surface_above);
+		}
+		if let Some((_window, subsurface_handle, sx, sy)) = self.get_window_and_subsurface_at(x, y) {
+			return Some((subsurface_handle, sx, sy));
+		}
+		self.get_layer_surface_at(x, y, false)
+	}
+
+	/// Returns the surface of a layer surface found at the provided coordinates and the coordinates relative to it.
+	/// Only the layers above the windows are checked if `above_windows` is true, only the ones below otherwise.
+	fn get_layer_surface_at(&self, x: f64, y: f64, above_windows: bool) -> Option<(WLRSurfaceHandle, f64, f64)> {
+		for output_data in self.output_data_map.values() {
+			if !output_data.area.contains_point(x, y) {
+				continue;
+			}
+			// ? The top layer is hidden by a fullscreen window
+			let top_layer_is_hidden = output_data.active_workspace().window_layout.has_fullscreen_window();
+			return output_data
+				.layer_surfaces
+				.iter()
+				.rev()
+				.filter(|layer_surface| layer_surface.is_mapped && layer_surface.is_above_windows() == above_windows)
+				.filter(|layer_surface| !(top_layer_is_hidden && layer_surface.state.layer == Layer::Top))
+				.find(|layer_surface| layer_surface.area.contains_point(x, y))
+				.and_then(|layer_surface| {
+					layer_surface.surface().map(|surface_handle| {
+						(
+							surface_handle,
+							x - f64::from(layer_surface.area.origin.x),
+							y - f64::from(layer_surface.area.origin.y),
+						)
+					})
+				});
+		}
+		None
+	}
+
 	/// Finds and removes the window bound to the provided shell handle from the containing output.
-	pub fn find_and_remove_window(&mut self, shell_handle: &WLRXdgV6ShellSurfaceHandle) -> bool {
-		if let Some(index_of_shell_handle) = self.window_stack.iter().position(|shell_handle_from_stack| *shell_handle_from_stack == *shell_handle) {
-			self.window_stack.remove(index_of_shell_handle);
+	pub fn find_and_remove_window(&mut self, shell_handle: &ShellHandle) -> bool {
+		if let Some(index_of_window) = self
+			.window_stack
+			.iter()
+			.position(|window_from_stack| window_from_stack.shell_handle == *shell_handle)
+		{
+			self.window_stack.remove(index_of_window);
 			return true;
 		}
+		for workspaces in self.parked_workspaces.values_mut() {
+			if let Some(workspace) = workspaces
+				.values_mut()
+				.find(|workspace| workspace.contains_shell_handle(shell_handle))
+			{
+				return workspace.remove_window_from_shell_handle(shell_handle).is_ok();
+			}
+		}
 
 		let mut fallback_shell_handle_option = None;
 		let mut name_of_container_output = None;
 		for (output_name, output_data) in self.output_data_map.iter_mut() {
-			if output_data.workspace.window_layout.contains_shell_handle(&shell_handle) {
-				match output_data
-					.workspace
-					.window_layout
-					.remove_window_from_shell_handle(&shell_handle, true)
-				{
+			let active_workspace_number = output_data.active_workspace_number;
+			let mut window_was_removed = false;
+			if let Some(workspace) = output_data.workspace_containing_shell_handle_mut(&shell_handle) {
+				match workspace.remove_window_from_shell_handle(&shell_handle) {
 					Err(e) => error!("{}", e),
 					Ok(_) => {
-						fallback_shell_handle_option = output_data.workspace.window_layout.get_active_shell_handle();
-						name_of_container_output = Some(output_name.clone());
-						break;
+						// ? Only the displayed workspace has a focus to fallback on
+						if workspace.number == active_workspace_number {
+							fallback_shell_handle_option = workspace.get_active_shell_handle();
+						}
+						window_was_removed = true;
 					}
 				}
 			}
+			if window_was_removed {
+				output_data.remove_empty_inactive_workspaces();
+				name_of_container_output = Some(output_name.clone());
+				break;
+			}
 		}
 
 		// TODO: Should fallback focus only if the containing output is the active one
@@ -292,32 +1420,53 @@ impl ComfyKernel {
 	}
 
 	/// Sets the shell of the provided shell handle as activated which means it will gain focus.
-	#[wlroots_dehandle(seat, keyboard, shell, surface)]
-	pub fn apply_keyboard_focus(&mut self, shell_handle: &WLRXdgV6ShellSurfaceHandle) {
+	#[wlroots_dehandle(seat, keyboard, surface)]
+	pub fn apply_keyboard_focus(&mut self, shell_handle: &ShellHandle) {
 		let seat_handle = self.seat_handle.clone().unwrap();
 		let keyboard_handle = self.keyboard_handle.clone().unwrap();
 
 		use keyboard_handle as keyboard;
 		use seat_handle as seat;
-		use shell_handle as shell;
 
-		shell.ping();
+		shell_handle.ping();
 
-		let surface_handle = shell.surface();
+		let surface_handle = match shell_handle.surface() {
+			Some(surface_handle) => surface_handle,
+			None => return,
+		};
 		use surface_handle as surface;
 
-		if let Some(&mut WLRXdgV6ShellState::TopLevel(ref mut toplevel)) = shell.state() {
-			toplevel.set_activated(true);
-		}
+		shell_handle.set_activated(true);
 		seat.set_keyboard(keyboard.input_device());
 		seat.keyboard_notify_enter(surface, &mut keyboard.keycodes(), &mut keyboard.get_modifier_masks());
 
 		// ? Finds the containing layout to find the containing node and set it as last activated
-		for (_output_name, output_data) in self.output_data_map.iter_mut() {
-			if output_data.workspace.window_layout.contains_shell_handle(&shell_handle) {
-				output_data.workspace.window_layout.set_as_last_activated(&shell_handle);
+		let mut containing_output_name_option = None;
+		for (output_name, output_data) in self.output_data_map.iter_mut() {
+			if let Some(workspace) = output_data.workspace_containing_shell_handle_mut(&shell_handle) {
+				workspace.set_as_last_activated(&shell_handle);
+				containing_output_name_option = Some(output_name.clone());
 			}
 		}
+		// ? The output of the focused window becomes the active one, new windows will be opened there
+		if let Some(containing_output_name) = containing_output_name_option {
+			self.active_output_name = containing_output_name;
+		}
+
+		let focused_window_info_option = self.find_window(&shell_handle).map(|window| window.get_info());
+		self.emit_event(IpcEvent::FocusChanged {
+			window: focused_window_info_option,
+		});
+	}
+
+	/// Removes the keyboard focus from the focused surface if any. (Example: When displaying an empty workspace)
+	#[wlroots_dehandle(seat)]
+	pub fn clear_keyboard_focus(&mut self) {
+		if let Some(ref seat_handle) = self.seat_handle {
+			use seat_handle as seat;
+			seat.keyboard_clear_focus();
+		}
+		self.emit_event(IpcEvent::FocusChanged { window: None });
 	}
 
 	/// Return a tuple for a window which contains a subsurface that intersects with the provided absolute coordinates.
@@ -326,6 +1475,20 @@ impl ComfyKernel {
 	// TODO: Maybe we should separate this into 2 separate functions. One that checks the active window and the other, the get_window_at...
 	pub fn get_window_and_subsurface_at(&mut self, x: f64, y: f64) -> Option<(Window, WLRSurfaceHandle, f64, f64)> {
 		let mut subsurface_intersection_at = None;
+		// ? Unmanaged and floating windows are displayed above the layout, they must be checked first
+		let unmanaged_window_option = self
+			.unmanaged_windows
+			.iter()
+			.rev()
+			.find(|window| window.area.contains_point(x, y))
+			.cloned();
+		// ? The area of a floating window includes it's borders, which hide the layout even without a surface under them
+		if let Some(window) = unmanaged_window_option.or_else(|| self.get_floating_window_at(x, y)) {
+			let (sx, sy) = window.convert_output_coord_to_window(x, y);
+			return self
+				.get_window_subsurface_at(&window, sx, sy)
+				.map(|(surface_handle, sx, sy)| (window, surface_handle, sx, sy));
+		}
 		if let Some(window) = self.get_active_window() {
 			let (sx, sy) = window.convert_output_coord_to_window(x, y);
 			if let Some((surface_handle, sx, sy)) = self.get_window_subsurface_at(&window, sx, sy) {
@@ -344,31 +1507,18 @@ impl ComfyKernel {
 	}
 
 	/// Returns a subsurface (and its offset) of the window that intersects the given the coordinates relative to the window's origin.
-	#[wlroots_dehandle(shell)]
 	fn get_window_subsurface_at(
 		&mut self,
 		window: &Window,
 		surface_x: f64,
 		surface_y: f64,
 	) -> Option<(WLRSurfaceHandle, f64, f64)> {
-		let mut subsurface_x = 0.0;
-		let mut subsurface_y = 0.0;
-		let mut surface_handle_option = None;
-		{
-			let shell_handle = &window.shell_handle;
-			use shell_handle as shell;
-			surface_handle_option = shell.surface_at(surface_x, surface_y, &mut subsurface_x, &mut subsurface_y);
-		}
-		if let Some(surface_handle) = surface_handle_option {
-			Some((surface_handle, subsurface_x, subsurface_y))
-		} else {
-			None
-		}
+		window.shell_handle.surface_at(surface_x, surface_y)
 	}
 
 	/// Returns the coordinates of the cursor location relative to the currently active output.
 	#[wlroots_dehandle(cursor)]
-	fn get_cursor_coordinates(&self) -> (f64, f64) {
+	pub fn get_cursor_coordinates(&self) -> (f64, f64) {
 		let cursor_handle = &self.cursor_handle;
 		use cursor_handle as cursor;
 		cursor.coords()
@@ -378,7 +1528,14 @@ impl ComfyKernel {
 	pub fn apply_focus_under_cursor(&mut self) {
 		let seat_handle = self.seat_handle.clone().unwrap();
 		let (cursor_x, cursor_y) = self.get_cursor_coordinates();
-		if let Some((window, subsurface_handle, sx, sy)) = self.get_window_and_subsurface_at(cursor_x, cursor_y) {
+		// ? Layer surfaces above the windows get the pointer first, the ones below only where there is no window
+		let layer_surface_above_option = self.get_layer_surface_at(cursor_x, cursor_y, true);
+		let window_option = if layer_surface_above_option.is_none() {
+			self.get_window_and_subsurface_at(cursor_x, cursor_y)
+		} else {
+			None
+		};
+		if let Some((window, subsurface_handle, sx, sy)) = window_option {
 			let mut should_apply_keyboard_focus = false;
 			{
 				use seat_handle as seat;
@@ -388,13 +1545,34 @@ impl ComfyKernel {
 					should_apply_keyboard_focus = true;
 				}
 			}
-			if should_apply_keyboard_focus {
+			// ? Unmanaged windows only receive the pointer, the keyboard stays on their parent window
+			if should_apply_keyboard_focus && window.shell_handle.is_top_level() {
 				let shell_handle = &window.shell_handle;
 				self.apply_keyboard_focus(shell_handle);
 			}
-		} else {
+		} else if let Some((subsurface_handle, sx, sy)) =
+			layer_surface_above_option.or_else(|| self.get_layer_surface_at(cursor_x, cursor_y, false))
+		{
 			use seat_handle as seat;
-			seat.pointer_clear_focus();
+			use subsurface_handle as subsurface;
+			if !seat.pointer_surface_has_focus(subsurface) {
+				seat.pointer_notify_enter(subsurface, sx, sy);
+			}
+		} else {
+			{
+				use seat_handle as seat;
+				seat.pointer_clear_focus();
+			}
+			// ? The borders of a floating window give it the focus as well, the pointer stays out of it's client
+			if let Some(window) = self.get_floating_window_at(cursor_x, cursor_y) {
+				let window_is_active = self
+					.get_active_window()
+					.map(|active_window| active_window.shell_handle == window.shell_handle)
+					.unwrap_or(false);
+				if !window_is_active {
+					self.apply_keyboard_focus(&window.shell_handle);
+				}
+			}
 		}
 	}
 
@@ -411,7 +1589,7 @@ impl ComfyKernel {
 		if let Some(ref seat_handle) = self.seat_handle.clone() {
 			use seat_handle as seat;
 			let (cursor_x, cursor_y) = self.get_cursor_coordinates();
-			if let Some((_window, _subsurface_handle, sx, sy)) = self.get_window_and_subsurface_at(cursor_x, cursor_y) {
+			if let Some((_subsurface_handle, sx, sy)) = self.get_surface_at(cursor_x, cursor_y) {
 				seat.pointer_notify_motion(time, sx, sy);
 			}
 		}
@@ -432,33 +1610,161 @@ impl ComfyKernel {
 		}
 	}
 
+	/// Displays the workspace associated with the provided number on the active output and gives the focus to it's
+	/// active window. The workspace is created if it doesn't exist yet.
+	pub fn switch_to_workspace(&mut self, workspace_number: u32) -> Result<(), String> {
+		let active_shell_option;
+		if let Some(output_data) = self.output_data_map.get_mut(&self.active_output_name) {
+			if output_data.active_workspace_number == workspace_number {
+				return Ok(());
+			}
+			output_data.set_active_workspace(workspace_number);
+			active_shell_option = output_data.active_workspace().get_active_shell_handle();
+		} else {
+			return Err(format!(
+				"Failed to get output data for active output: {}",
+				self.active_output_name
+			));
+		}
+
+		match active_shell_option {
+			Some(active_shell) => self.apply_keyboard_focus(&active_shell),
+			None => self.clear_keyboard_focus(),
+		}
+		self.schedule_frame_for_output(&self.active_output_name);
+		Ok(())
+	}
+
+	/// Moves the active window to the workspace associated with the provided number on the active output.
+	/// The workspace is created if it doesn't exist yet.
+	pub fn move_active_window_to_workspace(&mut self, workspace_number: u32) -> Result<(), String> {
+		let current_cursor_direction = self.cursor_direction.clone();
+		let fallback_shell_handle_option;
+		if let Some(output_data) = self.output_data_map.get_mut(&self.active_output_name) {
+			if output_data.active_workspace_number == workspace_number {
+				return Ok(());
+			}
+			let active_shell_handle = match output_data.active_workspace().get_active_shell_handle() {
+				Some(active_shell_handle) => active_shell_handle,
+				None => return Err("No active window found".to_string()),
+			};
+			let window_is_floating = output_data.active_workspace().floating_window_is_active;
+			match output_data
+				.active_workspace_mut()
+				.remove_window_from_shell_handle(&active_shell_handle)
+			{
+				Err(e) => return Err(e),
+				Ok(window) => {
+					let target_workspace = output_data.get_or_create_workspace(workspace_number);
+					if window_is_floating {
+						target_workspace.floating_windows.push(window);
+						target_workspace.floating_window_is_active = true;
+					} else if let Err(e) =
+						target_workspace
+							.window_layout
+							.add_window(window, &current_cursor_direction, true, true)
+					{
+						error!("{}", e);
+					}
+				}
+			}
+			fallback_shell_handle_option = output_data.active_workspace().get_active_shell_handle();
+		} else {
+			return Err(format!(
+				"Failed to get output data for active output: {}",
+				self.active_output_name
+			));
+		}
+
+		match fallback_shell_handle_option {
+			Some(fallback_shell_handle) => self.apply_keyboard_focus(&fallback_shell_handle),
+			None => self.clear_keyboard_focus(),
+		}
+		self.schedule_frame_for_output(&self.active_output_name);
+		Ok(())
+	}
+
 	/// Takes the active window and put itto the window stack
-	pub fn put_active_window_to_stack(&mut self) {
+	pub fn put_active_window_to_stack(&mut self) -> Result<(), String> {
 		if let Some(active_window) = self.get_active_window() {
-			let shell_handle = active_window.shell_handle;
-			if self.find_and_remove_window(&shell_handle) {
-				self.window_stack.push(shell_handle);
+			if self.find_and_remove_window(&active_window.shell_handle) {
+				self.window_stack.push(active_window);
 				info!("Pushed a window to the stack");
 			}
+			Ok(())
 		} else {
-			info!("No active window found");
+			Err("No active window found".to_string())
 		}
 	}
 
 	/// Takes a window from the stack if any and inserts it in the active output
-	pub fn pop_window_from_stack(&mut self) {
-		if let Some(shell_handle) = self.window_stack.pop() {
-			self.add_window_to_active_workspace(shell_handle);
-			info!("Poped window from the stack");
-		} else {
-			info!("No window to pop from the stack");
+	pub fn pop_window_from_stack(&mut self) -> Result<(), String> {
+		if !self.output_data_map.contains_key(&self.active_output_name) {
+			return Err(format!(
+				"Failed to get output data for active output: {}",
+				self.active_output_name
+			));
 		}
+		let window = self
+			.window_stack
+			.pop()
+			.ok_or_else(|| "No window to pop from the stack".to_string())?;
+		self.insert_window_in_active_workspace(window)?;
+		info!("Poped window from the stack");
+		Ok(())
+	}
+
+	/// Inserts a window taken out of the workspaces before in the layout of the active workspace, then gives it the
+	/// focus. The window is inserted as it is, so it keeps it's id.
+	pub fn insert_window_in_active_workspace(&mut self, window: Window) -> Result<(), String> {
+		let shell_handle = window.shell_handle.clone();
+		let current_cursor_direction = self.cursor_direction.clone();
+		let active_output_name = self.active_output_name.clone();
+		{
+			let workspace = self
+				.get_active_workspace_mut()
+				.ok_or_else(|| format!("Failed to get output data for active output: {}", active_output_name))?;
+			workspace
+				.window_layout
+				.add_window(window, &current_cursor_direction, true, true)?;
+		}
+		self.apply_keyboard_focus(&shell_handle);
+		self.schedule_frame_for_output(&self.active_output_name);
+		Ok(())
+	}
+
+	/// Takes the window bound to the provided shell handle out of it's workspace while it's hidden by it's client, see
+	/// `unmapped_windows`.
+	pub fn unmap_window(&mut self, shell_handle: &ShellHandle) {
+		if let Some(window) = self.find_window(shell_handle) {
+			if self.find_and_remove_window(shell_handle) {
+				self.unmapped_windows.push(window);
+			}
+		}
+	}
+
+	/// Removes the window bound to the provided shell handle from the unmapped windows, if it's one of them.
+	pub fn take_unmapped_window(&mut self, shell_handle: &ShellHandle) -> Option<Window> {
+		let index = self
+			.unmapped_windows
+			.iter()
+			.position(|window| window.shell_handle == *shell_handle)?;
+		Some(self.unmapped_windows.remove(index))
+	}
+
+	/// Returns the top floating window of the displayed workspaces found at the provided coordinates.
+	fn get_floating_window_at(&self, x: f64, y: f64) -> Option<Window> {
+		self
+			.output_data_map
+			.values()
+			.filter_map(|output_data| output_data.active_workspace().find_floating_window_at(x, y))
+			.next()
 	}
 
 	fn get_window_at(&mut self, x: f64, y: f64) -> Option<Window> {
 		let mut window = None;
 		for (_, output_data) in self.output_data_map.iter_mut() {
-			window = output_data.workspace.window_layout.find_window_at(x, y);
+			window = output_data.active_workspace_mut().find_window_at(x, y);
 			if window.is_some() {
 				break;
 			}
@@ -475,13 +1781,146 @@ impl ComfyKernel {
 		cursor.warp_absolute(event.device(), absolute_x, absolute_y);
 	}
 
-	/// Returns the command associated with the provided key_set if any.
-	pub fn command_for_keyset(&self, key_set: &XkbKeySet) -> Option<Command> {
-		if self.config.keybindings.bindings.contains_key(&key_set) {
-			let command = self.config.keybindings.bindings.get(&key_set).unwrap().clone();
-			Some(command)
-		} else {
-			None
+	/// Looks the provided key_set up in the bindings of the current binding mode, after the keysets of the sequence
+	/// being typed unless it timed out.
+	pub fn match_keyset(&mut self, key_set: &XkbKeySet) -> BindingMatch {
+		let pending_key_sets = match self.pending_key_sequence.take() {
+			Some((key_sets, pressed_at)) => {
+				if pressed_at.elapsed() <= self.config.keybindings.sequence_timeout {
+					key_sets
+				} else {
+					debug!("The key sequence timed out");
+					Vec::new()
+				}
+			}
+			None => Vec::new(),
+		};
+		let (binding_match, starts_sequence) = match self
+			.config
+			.keybindings
+			.bindings_for_mode(&self.binding_mode)
+			.and_then(|bindings| bindings.sequence_bindings(&pending_key_sets))
+		{
+			Some(bindings) => match bindings.lookup(key_set) {
+				Some(Binding::Command(bound_command)) => (BindingMatch::Command(bound_command.clone()), false),
+				Some(Binding::Sequence(_)) => (BindingMatch::Pending, true),
+				// ? The modifiers of the next keyset are pressed before it's last key
+				None if !pending_key_sets.is_empty() && bindings.has_key_set_containing(key_set) => {
+					(BindingMatch::Pending, false)
+				}
+				None => (BindingMatch::Unbound, false),
+			},
+			None => (BindingMatch::Unbound, false),
+		};
+
+		match binding_match {
+			BindingMatch::Pending => {
+				let mut key_sets = pending_key_sets;
+				if starts_sequence {
+					key_sets.push(key_set.clone());
+				}
+				self.pending_key_sequence = Some((key_sets, Instant::now()));
+				BindingMatch::Pending
+			}
+			// ? The sequence is abandoned, the keys are looked up from the start of the bindings
+			BindingMatch::Unbound if !pending_key_sets.is_empty() => self.match_keyset(key_set),
+			binding_match => binding_match,
+		}
+	}
+
+	/// Returns the command bound to the provided mouse input with the keys currently pressed if any. The mouse
+	/// bindings are only used in the default binding mode.
+	pub fn command_for_mouse_input(&self, mouse_input: MouseInput) -> Option<Command> {
+		if self.binding_mode != DEFAULT_MODE {
+			return None;
+		}
+		self
+			.config
+			.keybindings
+			.mousebindings
+			.get(&(self.currently_pressed_keys.clone(), mouse_input))
+			.cloned()
+	}
+
+	/// Makes the bindings of the provided mode the only ones looked up, the other keys are sent to the clients.
+	pub fn enter_binding_mode(&mut self, mode_name: &str) -> Result<(), String> {
+		if !self.config.keybindings.has_mode(mode_name) {
+			return Err(format!("Unknown binding mode: {}", mode_name));
+		}
+		if self.binding_mode != mode_name {
+			info!("Entering the binding mode {}", mode_name);
+			self.binding_mode = mode_name.to_string();
+			self.pending_key_sequence = None;
+			self.emit_event(IpcEvent::ModeChanged {
+				mode: mode_name.to_string(),
+			});
+		}
+		Ok(())
+	}
+
+	/// Sets the keymap described by the keyboard configuration on the keyboard, which starts on it's first layout.
+	/// Setting a keymap emits the modifiers event of the keyboard, whose handler borrows the compositor, so the keymap
+	/// is set once the event loop is idle.
+	pub fn apply_keyboard_config(&mut self) {
+		self.defer(DeferredAction::ApplyKeyboardConfig);
+	}
+
+	/// Returns the keyboard with the keymap compiled from the keyboard configuration, if there is a keyboard.
+	pub fn keyboard_keymap_change(&self) -> Option<(*mut wlr_keyboard, xkb::Keymap)> {
+		let keyboard_ptr = self.keyboard_ptr()?;
+		match compile_keymap(&self.config.global.keyboard) {
+			Ok(keymap) => Some((keyboard_ptr, keymap)),
+			Err(e) => {
+				error!("{}", e);
+				None
+			}
+		}
+	}
+
+	/// Returns the wlroots structure of the keyboard, to make the calls emitting it's events once it's released.
+	#[wlroots_dehandle(keyboard)]
+	pub fn keyboard_ptr(&self) -> Option<*mut wlr_keyboard> {
+		let keyboard_handle = self.keyboard_handle.clone()?;
+		use keyboard_handle as keyboard;
+		Some(unsafe { keyboard.as_ptr() })
+	}
+
+	/// Returns the index of the layout used by the keyboard in it's keymap, if there is a keyboard. A switch waiting for
+	/// the event loop is taken as done, so the layouts around it are the next and previous ones.
+	#[wlroots_dehandle(keyboard)]
+	pub fn active_keyboard_layout(&self) -> Option<u32> {
+		let keyboard_handle = self.keyboard_handle.clone()?;
+		let pending_layout_index_option = self
+			.deferred_actions
+			.iter()
+			.filter_map(|deferred_action| match deferred_action {
+				DeferredAction::SwitchKeyboardLayout(layout_index) => Some(*layout_index),
+				_ => None,
+			}).last();
+		if pending_layout_index_option.is_some() {
+			return pending_layout_index_option;
+		}
+		use keyboard_handle as keyboard;
+		Some(keyboard.get_modifier_masks().group)
+	}
+
+	/// Makes the keyboard use the layout at the provided index of it's keymap once the event loop is idle, since it
+	/// emits the modifiers event of the keyboard. The seat then sends the layout to the clients.
+	pub fn set_keyboard_layout(&mut self, layout_index: u32) -> Result<(), String> {
+		if self.keyboard_handle.is_none() {
+			return Err("No keyboard to switch the layout of".to_string());
+		}
+		self.defer(DeferredAction::SwitchKeyboardLayout(layout_index));
+		Ok(())
+	}
+
+	/// Sends the clients the key press withheld by an `on_release` binding, if any.
+	#[wlroots_dehandle(seat)]
+	pub fn replay_withheld_key_press(&mut self) {
+		if let Some((time_msec, keycode)) = self.withheld_key_press.take() {
+			let seat_handle = self.seat_handle.clone().unwrap();
+			use seat_handle as seat;
+			seat.keyboard_notify_key(time_msec, keycode, WLR_KEY_PRESSED as u32);
 		}
 	}
 
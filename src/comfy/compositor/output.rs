@@ -1,13 +1,30 @@
+use wlroots::wlroots_sys::{
+	wl_list, wl_output_transform, wlr_output, wlr_output_mode, wlr_output_set_custom_mode, wlr_output_set_mode,
+	wlr_output_set_scale, wlr_output_set_transform,
+};
 use wlroots::{
-	Area, CompositorHandle as WLRCompositorHandle, Origin, OutputBuilder as WLROutputBuilder,
+	Area, CompositorHandle as WLRCompositorHandle, Origin, Output as WLROutput, OutputBuilder as WLROutputBuilder,
 	OutputBuilderResult as WLROutputBuilderResult, OutputHandle as WLROutputHandle, OutputHandler as WLROutputHandler,
-	OutputLayoutHandler as WLROutputLayoutHandler, /* , OutputDestruction as WLROutputDestruction */
-	OutputManagerHandler as WLROutputManagerHandler, Size,
+	OutputLayout as WLROutputLayout, OutputLayoutHandler as WLROutputLayoutHandler,
+	/* , OutputDestruction as WLROutputDestruction */ OutputManagerHandler as WLROutputManagerHandler,
+	Renderer as WLRRenderer, Size,
 };
 
-use common::colors::Color;
+use std::collections::BTreeMap;
+use std::ptr;
+
+use common::ipc::IpcEvent;
+use compositor::deferred::DeferredAction;
+use compositor::layer_shell::{Layer, LayerSurface};
+use compositor::shell_handle::ShellHandle;
 use compositor::workspace::Workspace;
 use compositor::ComfyKernel;
+use config::output::{OutputConfig, OutputTransform};
+use config::theme::WallpaperMode;
+use layout::LayoutDirection;
+
+/// Number of the workspace displayed when an output is added.
+pub const DEFAULT_WORKSPACE_NUMBER: u32 = 1;
 
 /*
 ..####...##..##..######..#####...##..##..######..#####....####...######...####..
@@ -19,16 +36,141 @@ use compositor::ComfyKernel;
 */
 
 pub struct OutputData {
-	pub workspace: Workspace,
-	pub clear_color: [f32; 4],
+	pub area: Area,
+	/// Area of the output left to the workspaces by the exclusive zones of the layer surfaces.
+	pub usable_area: Area,
+	pub layer_surfaces: Vec<LayerSurface>,
+	pub workspaces: BTreeMap<u32, Workspace>,
+	pub active_workspace_number: u32,
 }
 
 impl OutputData {
-	/// Creates data for an output which contains a workspace with a layout with the given area.
+	/// Creates data for an output which contains a single workspace with a layout with the given area.
 	pub fn new(area: Area) -> Self {
+		let mut workspaces = BTreeMap::new();
+		workspaces.insert(DEFAULT_WORKSPACE_NUMBER, Workspace::new(DEFAULT_WORKSPACE_NUMBER, area));
 		OutputData {
-			workspace: Workspace::new(area),
-			clear_color: Color::burgundy().as_rgba_slice(),
+			area,
+			usable_area: area,
+			layer_surfaces: Vec::new(),
+			workspaces,
+			active_workspace_number: DEFAULT_WORKSPACE_NUMBER,
+		}
+	}
+
+	/// Returns the workspace displayed on the output.
+	pub fn active_workspace(&self) -> &Workspace {
+		self
+			.workspaces
+			.get(&self.active_workspace_number)
+			.expect("The active workspace of an output should always exist")
+	}
+
+	/// Returns the workspace displayed on the output.
+	pub fn active_workspace_mut(&mut self) -> &mut Workspace {
+		self
+			.workspaces
+			.get_mut(&self.active_workspace_number)
+			.expect("The active workspace of an output should always exist")
+	}
+
+	/// Returns the workspace associated with the provided number, creating it if it doesn't exist yet.
+	pub fn get_or_create_workspace(&mut self, workspace_number: u32) -> &mut Workspace {
+		let area = self.usable_area;
+		self
+			.workspaces
+			.entry(workspace_number)
+			.or_insert_with(|| Workspace::new(workspace_number, area))
+	}
+
+	/// Displays the workspace associated with the provided number, creating it if it doesn't exist yet.
+	/// The previously displayed workspace is dropped if it doesn't contain any window.
+	pub fn set_active_workspace(&mut self, workspace_number: u32) {
+		self.get_or_create_workspace(workspace_number);
+		self.active_workspace_number = workspace_number;
+		self.remove_empty_inactive_workspaces();
+	}
+
+	/// Returns the number of the workspace following the displayed one, wrapping around to the first one.
+	pub fn next_workspace_number(&self) -> u32 {
+		let active_workspace_number = self.active_workspace_number;
+		self
+			.workspaces
+			.keys()
+			.find(|workspace_number| **workspace_number > active_workspace_number)
+			.or_else(|| self.workspaces.keys().next())
+			.cloned()
+			.unwrap_or(active_workspace_number)
+	}
+
+	/// Returns the number of the workspace preceding the displayed one, wrapping around to the last one.
+	pub fn previous_workspace_number(&self) -> u32 {
+		let active_workspace_number = self.active_workspace_number;
+		self
+			.workspaces
+			.keys()
+			.rev()
+			.find(|workspace_number| **workspace_number < active_workspace_number)
+			.or_else(|| self.workspaces.keys().next_back())
+			.cloned()
+			.unwrap_or(active_workspace_number)
+	}
+
+	/// Drops every workspace that isn't displayed and doesn't contain any window.
+	pub fn remove_empty_inactive_workspaces(&mut self) {
+		let active_workspace_number = self.active_workspace_number;
+		let numbers_of_workspaces_to_remove: Vec<u32> = self
+			.workspaces
+			.values()
+			.filter(|workspace| workspace.number != active_workspace_number && workspace.is_empty())
+			.map(|workspace| workspace.number)
+			.collect();
+		for workspace_number in numbers_of_workspaces_to_remove {
+			self.workspaces.remove(&workspace_number);
+		}
+	}
+
+	/// Returns the workspace which contains the provided shell handle if any.
+	pub fn workspace_containing_shell_handle_mut(&mut self, shell_handle: &ShellHandle) -> Option<&mut Workspace> {
+		self
+			.workspaces
+			.values_mut()
+			.find(|workspace| workspace.contains_shell_handle(shell_handle))
+	}
+
+	/// Updates the area of the output, then rebalances the layout of each of it's workspaces.
+	pub fn update_area_and_rebalance(&mut self, area: Area) {
+		self.area = area;
+		self.arrange_layer_surfaces();
+		self.rebalance_workspaces();
+	}
+
+	/// Places the layer surfaces on the output and computes the usable area left by their exclusive zones.
+	/// Returns true if the usable area changed, in which case the workspaces need to be rebalanced.
+	pub fn arrange_layer_surfaces(&mut self) -> bool {
+		let mut usable_area = self.area;
+		// ? The surfaces with an exclusive zone are placed first, so the others can avoid them
+		for should_have_exclusive_zone in &[true, false] {
+			for layer in Layer::all_from_the_top() {
+				for layer_surface in self.layer_surfaces.iter_mut() {
+					if layer_surface.state.layer == layer
+						&& (layer_surface.state.exclusive_zone > 0) == *should_have_exclusive_zone
+					{
+						layer_surface.arrange(&self.area, &mut usable_area);
+					}
+				}
+			}
+		}
+		let usable_area_changed = usable_area != self.usable_area;
+		self.usable_area = usable_area;
+		usable_area_changed
+	}
+
+	/// Rebalances the layout of each workspace inside the usable area.
+	pub fn rebalance_workspaces(&mut self) {
+		let usable_area = self.usable_area;
+		for workspace in self.workspaces.values_mut() {
+			workspace.window_layout.update_area_and_rebalance(usable_area);
 		}
 	}
 }
@@ -48,6 +190,271 @@ impl OutputData {
 ........................................................
 */
 
+/// Mode, scale and transform to apply on an output. Setting the mode emits the mode event of the output right away,
+/// whose handler borrows the compositor and the output, so the changes are applied once none of them is borrowed.
+#[derive(Clone)]
+pub struct OutputChange {
+	pub output: *mut wlr_output,
+	pub output_name: String,
+	pub mode: Option<OutputModeChange>,
+	pub scale: Option<f32>,
+	pub transform: Option<wl_output_transform>,
+}
+
+#[derive(Clone)]
+pub enum OutputModeChange {
+	/// Index of the mode in the list of modes of the output.
+	Listed(usize),
+	/// Mode not listed by the output, only some backends support them. (Example: nested inside another compositor)
+	Custom { width: i32, height: i32, refresh: i32 },
+}
+
+/// Returns the mode, scale and transform requested by the configuration of the output.
+pub fn output_change_from_config(output: &mut WLROutput, output_config: &OutputConfig) -> OutputChange {
+	let mode = output_config.mode.as_ref().map(|mode_config| {
+		// ? Without a requested refresh rate, the highest one is used
+		let best_mode_index_option = output
+			.modes()
+			.into_iter()
+			.enumerate()
+			.filter(|&(_, ref mode)| mode.dimensions() == (mode_config.width, mode_config.height))
+			.min_by_key(|&(_, ref mode)| match mode_config.refresh {
+				Some(refresh) => (mode.refresh() - refresh).abs(),
+				None => -mode.refresh(),
+			}).map(|(index, _)| index);
+		match best_mode_index_option {
+			Some(index) => OutputModeChange::Listed(index),
+			None => OutputModeChange::Custom {
+				width: mode_config.width,
+				height: mode_config.height,
+				refresh: mode_config.refresh.unwrap_or(0),
+			},
+		}
+	});
+	OutputChange {
+		output: unsafe { output.as_ptr() },
+		output_name: output.name(),
+		mode,
+		scale: output_config.scale,
+		transform: output_config.transform.as_ref().map(wlr_output_transform),
+	}
+}
+
+/// Returns the changes bringing an output back to the state it was added with, the last mode it lists like
+/// `build_best_mode`. (Example: after it's section was removed from the config)
+pub fn default_output_change(output: &mut WLROutput) -> OutputChange {
+	let mode_count = output.modes().len();
+	OutputChange {
+		output: unsafe { output.as_ptr() },
+		output_name: output.name(),
+		mode: if mode_count > 0 {
+			Some(OutputModeChange::Listed(mode_count - 1))
+		} else {
+			None
+		},
+		scale: Some(1.0),
+		transform: Some(wl_output_transform::WL_OUTPUT_TRANSFORM_NORMAL),
+	}
+}
+
+/// Applies the changes on their output. Must be called while neither the compositor nor the output is borrowed.
+/// Returns false if the mode could not be set.
+pub unsafe fn apply_output_change(output_change: &OutputChange) -> bool {
+	let output = output_change.output;
+	let mode_was_set = match output_change.mode {
+		Some(OutputModeChange::Listed(index)) => {
+			let mode = output_mode_at(output, index);
+			// ? Setting the same mode again would modeset the output for nothing
+			mode.is_null() || (*output).current_mode == mode || wlr_output_set_mode(output, mode)
+		}
+		Some(OutputModeChange::Custom { width, height, refresh }) => {
+			((*output).width == width && (*output).height == height && (*output).refresh == refresh)
+				|| wlr_output_set_custom_mode(output, width, height, refresh)
+		}
+		None => true,
+	};
+	if !mode_was_set {
+		warn!(
+			"Could not set the requested mode on the output {}",
+			output_change.output_name
+		);
+	}
+	if let Some(scale) = output_change.scale {
+		wlr_output_set_scale(output, scale);
+	}
+	if let Some(transform) = output_change.transform {
+		wlr_output_set_transform(output, transform);
+	}
+	mode_was_set
+}
+
+/// Returns the index of the mode in the list of modes of the output, if it's one of them.
+pub unsafe fn output_mode_index(output: *mut wlr_output, mode: *mut wlr_output_mode) -> Option<usize> {
+	let modes: *mut wl_list = &mut (*output).modes;
+	let mut link = (*modes).next;
+	let mut index = 0;
+	while link != modes {
+		if container_of!(link, wlr_output_mode, link) == mode {
+			return Some(index);
+		}
+		link = (*link).next;
+		index += 1;
+	}
+	None
+}
+
+/// Returns the mode at the provided index of the list of modes of the output, null if the list is shorter.
+unsafe fn output_mode_at(output: *mut wlr_output, index: usize) -> *mut wlr_output_mode {
+	let modes: *mut wl_list = &mut (*output).modes;
+	let mut link = (*modes).next;
+	for _ in 0..index {
+		if link == modes {
+			break;
+		}
+		link = (*link).next;
+	}
+	if link == modes {
+		ptr::null_mut()
+	} else {
+		container_of!(link, wlr_output_mode, link)
+	}
+}
+
+/// Adds the output to the layout at the position of it's configuration, or next to the other outputs otherwise.
+pub fn place_output(
+	output_layout: &mut WLROutputLayout,
+	output: &mut WLROutput,
+	output_config_option: Option<&OutputConfig>,
+) {
+	match output_config_option.and_then(|output_config| output_config.position) {
+		Some((x, y)) => output_layout.add(output, Origin::new(x, y)),
+		None => output_layout.add_auto(output),
+	}
+}
+
+/// Returns the name of the closest output in a direction from the provided area. The outputs are compared by the gap
+/// between them and the area, then by how far they are from being aligned with it.
+pub fn find_output_in_direction<'a, I>(output_areas: I, area: &Area, direction: &LayoutDirection) -> Option<String>
+where
+	I: Iterator<Item = (&'a String, &'a Area)>,
+{
+	let center_x = area.origin.x + area.size.width / 2;
+	let center_y = area.origin.y + area.size.height / 2;
+	output_areas
+		.filter_map(|(output_name, output_area)| {
+			let output_center_x = output_area.origin.x + output_area.size.width / 2;
+			let output_center_y = output_area.origin.y + output_area.size.height / 2;
+			let (gap, misalignment) = match direction {
+				LayoutDirection::Up => (
+					area.origin.y - (output_area.origin.y + output_area.size.height),
+					output_center_x - center_x,
+				),
+				LayoutDirection::Down => (
+					output_area.origin.y - (area.origin.y + area.size.height),
+					output_center_x - center_x,
+				),
+				LayoutDirection::Left => (
+					area.origin.x - (output_area.origin.x + output_area.size.width),
+					output_center_y - center_y,
+				),
+				LayoutDirection::Right => (
+					output_area.origin.x - (area.origin.x + area.size.width),
+					output_center_y - center_y,
+				),
+			};
+			// ? A negative gap means the output overlaps the area or is on the other side
+			if gap < 0 {
+				None
+			} else {
+				Some((gap, misalignment.abs(), output_name))
+			}
+		})
+		.min()
+		.map(|(_, _, output_name)| output_name.clone())
+}
+
+fn wlr_output_transform(transform: &OutputTransform) -> wl_output_transform {
+	match transform {
+		OutputTransform::Normal => wl_output_transform::WL_OUTPUT_TRANSFORM_NORMAL,
+		OutputTransform::Rotated90 => wl_output_transform::WL_OUTPUT_TRANSFORM_90,
+		OutputTransform::Rotated180 => wl_output_transform::WL_OUTPUT_TRANSFORM_180,
+		OutputTransform::Rotated270 => wl_output_transform::WL_OUTPUT_TRANSFORM_270,
+		OutputTransform::Flipped => wl_output_transform::WL_OUTPUT_TRANSFORM_FLIPPED,
+		OutputTransform::Flipped90 => wl_output_transform::WL_OUTPUT_TRANSFORM_FLIPPED_90,
+		OutputTransform::Flipped180 => wl_output_transform::WL_OUTPUT_TRANSFORM_FLIPPED_180,
+		OutputTransform::Flipped270 => wl_output_transform::WL_OUTPUT_TRANSFORM_FLIPPED_270,
+	}
+}
+
+/// Returns the areas, in pixels of the output, where the wallpaper is drawn with the provided mode.
+fn wallpaper_areas(wallpaper_mode: &WallpaperMode, output_size: Size, texture_size: Size) -> Vec<Area> {
+	if texture_size.width <= 0 || texture_size.height <= 0 {
+		return Vec::new();
+	}
+	let centered_area = |size: Size| {
+		Area::new(
+			Origin::new(
+				(output_size.width - size.width) / 2,
+				(output_size.height - size.height) / 2,
+			),
+			size,
+		)
+	};
+	let scaled_size = |scale: f32| {
+		Size::new(
+			(texture_size.width as f32 * scale).round() as i32,
+			(texture_size.height as f32 * scale).round() as i32,
+		)
+	};
+	let scale_x = output_size.width as f32 / texture_size.width as f32;
+	let scale_y = output_size.height as f32 / texture_size.height as f32;
+	match wallpaper_mode {
+		WallpaperMode::Fill => vec![centered_area(scaled_size(scale_x.max(scale_y)))],
+		WallpaperMode::Fit => vec![centered_area(scaled_size(scale_x.min(scale_y)))],
+		WallpaperMode::Center => vec![centered_area(texture_size)],
+		WallpaperMode::Stretch => vec![Area::new(Origin::new(0, 0), output_size)],
+		WallpaperMode::Tile => {
+			let mut tile_areas = Vec::new();
+			for y in (0..output_size.height).step_by(texture_size.height as usize) {
+				for x in (0..output_size.width).step_by(texture_size.width as usize) {
+					tile_areas.push(Area::new(Origin::new(x, y), texture_size));
+				}
+			}
+			tile_areas
+		}
+	}
+}
+
+/// Returns the projection which renders a texture, drawn at the origin, inside the provided area of the output.
+/// (Projection of the output * translation to the area * scale from the texture to the area)
+fn project_texture_in_area(output_projection: [f32; 9], area: &Area, texture_size: Size) -> [f32; 9] {
+	let scale_x = area.size.width as f32 / texture_size.width as f32;
+	let scale_y = area.size.height as f32 / texture_size.height as f32;
+	let (x, y) = (area.origin.x as f32, area.origin.y as f32);
+	let mut projection = [0.0; 9];
+	for row in 0..3 {
+		let (column_x, column_y, column_z) = (
+			output_projection[row * 3],
+			output_projection[row * 3 + 1],
+			output_projection[row * 3 + 2],
+		);
+		projection[row * 3] = column_x * scale_x;
+		projection[row * 3 + 1] = column_y * scale_y;
+		projection[row * 3 + 2] = column_x * x + column_y * y + column_z;
+	}
+	projection
+}
+
+/// Renders the layer surfaces of the provided layer, on the output whose top left corner is at the provided origin.
+fn render_layer(renderer: &mut WLRRenderer, output_origin: Origin, layer_surfaces: &[LayerSurface], layer: Layer) {
+	for layer_surface in layer_surfaces
+		.iter()
+		.filter(|layer_surface| layer_surface.is_mapped && layer_surface.state.layer == layer)
+	{
+		layer_surface.render(renderer, output_origin);
+	}
+}
+
 // ? Handles events on the output layout (how displays are organized)
 pub struct OutputLayoutHandler;
 impl WLROutputLayoutHandler for OutputLayoutHandler {}
@@ -62,7 +469,9 @@ impl WLROutputHandler for OutputHandler {
 		use output_handle as output;
 		let output_name = output.name().clone();
 		let (output_width, output_height) = output.effective_resolution();
-		let mut transform_matrix = output.transform_matrix();
+		let transform_matrix = output.transform_matrix();
+		let output_ptr = unsafe { output.as_ptr() };
+		let output_scale = output.scale();
 		let comfy_kernel: &mut ComfyKernel = compositor.data.downcast_mut().unwrap();
 		let renderer = compositor
 			.renderer
@@ -72,52 +481,111 @@ impl WLROutputHandler for OutputHandler {
 
 		// ? Clearing the screen and get indices of windows to render
 		if comfy_kernel.should_load_wallpaper {
-			comfy_kernel.load_wallpaper(renderer);
+			comfy_kernel.load_wallpapers(renderer);
 		}
-		let wallpaper_option = &comfy_kernel.wallpaper_texture;
+		let wallpaper_textures = &comfy_kernel.wallpaper_textures;
+		let wallpaper_option = comfy_kernel
+			.config
+			.theme
+			.wallpaper_path_for(&output_name)
+			.and_then(|wallpaper_path| wallpaper_textures.get(wallpaper_path));
+		let wallpaper_mode = &comfy_kernel.config.theme.wallpaper_mode;
+		let background_color = comfy_kernel.config.theme.background_color.as_slice();
 		let active_color = &comfy_kernel.config.theme.active_color.as_slice();
 		let inactive_color = &comfy_kernel.config.theme.inactive_color.as_slice();
 		let cursor_indicator_color = &comfy_kernel.config.theme.cursor_indicator_color.as_slice();
 		let cursor_orentation = comfy_kernel.cursor_direction.clone();
-		if let Some(OutputData {
-			workspace, clear_color, ..
-		}) = comfy_kernel.output_data_map.get_mut(&output_name)
-		{
-			// ? Clear the screen with an image or the render color otherwise
+		if let Some(output_data) = comfy_kernel.output_data_map.get_mut(&output_name) {
+			let layer_surfaces = output_data.layer_surfaces.clone();
+			// ? Everything is placed in layout coordinates, then drawn relative to the output
+			let output_origin = output_data.area.origin;
+			// ? Only the displayed workspace is rendered, the others keep their layout untouched
+			let workspace = output_data.active_workspace_mut();
+
+			// ? Clear the screen with the background color, then draws the wallpaper over it if any
+			render_context.clear(background_color);
 			if let Some(wallpaper_texture) = wallpaper_option {
 				let (texture_width, texture_height) = wallpaper_texture.size();
-				let (scale_x, scale_y) = (
-					output_width as f32 / texture_width as f32,
-					output_height as f32 / texture_height as f32,
+				// ? The projection of the output works with pixels, not with the effective resolution
+				let output_size = Size::new(
+					(output_width as f32 * output_scale) as i32,
+					(output_height as f32 * output_scale) as i32,
 				);
-				transform_matrix[0] = transform_matrix[0] * scale_x;
-				transform_matrix[4] = transform_matrix[4] * scale_y;
-				render_context.render_texture(&wallpaper_texture, transform_matrix, 0, 0, 1.0);
-			} else {
-				render_context.clear(clear_color.clone());
+				let texture_size = Size::new(texture_width as i32, texture_height as i32);
+				for wallpaper_area in wallpaper_areas(wallpaper_mode, output_size, texture_size) {
+					let wallpaper_matrix = project_texture_in_area(transform_matrix, &wallpaper_area, texture_size);
+					render_context.render_texture(&wallpaper_texture, wallpaper_matrix, 0, 0, 1.0);
+				}
 			}
 
+			// ? The background and bottom layers are displayed below the windows
+			render_layer(&mut render_context, output_origin, &layer_surfaces, Layer::Background);
+			render_layer(&mut render_context, output_origin, &layer_surfaces, Layer::Bottom);
+
 			// ? Renders all windows
 			if !workspace.window_layout.should_only_render_active_window() {
 				workspace.window_layout.for_each_non_active_window(|window_ref| {
 					if window_ref.has_active_animation() {
 						window_ref.progress_animation();
 					}
-					window_ref.render_all_surfaces(&mut render_context, inactive_color, None, None);
+					window_ref.render_all_surfaces(&mut render_context, output_origin, inactive_color, None, None);
 				});
 			}
 
+			// ? The active window of the layout loses it's highlight while a floating window has the focus
+			let floating_window_is_active = workspace.floating_window_is_active;
 			workspace.window_layout.apply_to_active_window(|window_ref| {
 				if window_ref.has_active_animation() {
 					window_ref.progress_animation();
 				}
-				window_ref.render_all_surfaces(
-					&mut render_context,
-					active_color,
-					Some(&cursor_orentation),
-					Some(cursor_indicator_color),
-				);
+				if floating_window_is_active {
+					window_ref.render_all_surfaces(&mut render_context, output_origin, inactive_color, None, None);
+				} else {
+					window_ref.render_all_surfaces(
+						&mut render_context,
+						output_origin,
+						active_color,
+						Some(&cursor_orentation),
+						Some(cursor_indicator_color),
+					);
+				}
 			});
+
+			// ? Renders the floating windows above the layout, from the bottom to the top, unless a window is in fullscreen
+			if !workspace.window_layout.has_fullscreen_window() {
+				let index_of_top_window = workspace.floating_windows.len().saturating_sub(1);
+				for (index, window_ref) in workspace.floating_windows.iter_mut().enumerate() {
+					if window_ref.has_active_animation() {
+						window_ref.progress_animation();
+					}
+					let border_color = if floating_window_is_active && index == index_of_top_window {
+						active_color
+					} else {
+						inactive_color
+					};
+					window_ref.render_all_surfaces(&mut render_context, output_origin, border_color, None, None);
+				}
+			}
+
+			// ? The top layer is hidden by a fullscreen window, the overlay layer is always displayed
+			if !workspace.window_layout.has_fullscreen_window() {
+				render_layer(&mut render_context, output_origin, &layer_surfaces, Layer::Top);
+			}
+			render_layer(&mut render_context, output_origin, &layer_surfaces, Layer::Overlay);
+
+			// ? Unmanaged X11 surfaces (menus, tooltips, etc...) are always on top
+			for window_ref in comfy_kernel.unmanaged_windows.iter() {
+				window_ref.render_all_surfaces(&mut render_context, output_origin, &background_color, None, None);
+			}
+
+			// ? The screenshots are read back once everything was rendered, before the frame is swapped
+			if let Some(pending_screenshots) = comfy_kernel.pending_screenshots.remove(&output_name) {
+				for pending_screenshot in pending_screenshots {
+					if let Err(e) = unsafe { pending_screenshot.capture(output_ptr, &output_data.area, output_scale) } {
+						error!("Could not take the screenshot: {}", e);
+					}
+				}
+			}
 		}
 	}
 
@@ -137,10 +605,7 @@ impl WLROutputHandler for OutputHandler {
 		if let Some(output_data) = output_data_map.get_mut(&output.name()) {
 			let (x, y) = output.layout_space_pos();
 			let (width, height) = output.effective_resolution();
-			output_data
-				.workspace
-				.window_layout
-				.update_area_and_rebalance(Area::new(Origin::new(x, y), Size::new(width, height)));
+			output_data.update_area_and_rebalance(Area::new(Origin::new(x, y), Size::new(width, height)));
 		}
 	}
 
@@ -172,11 +637,9 @@ impl WLROutputHandler for OutputHandler {
 		let comfy_kernel: &mut ComfyKernel = compositor.data.downcast_mut().unwrap();
 		let output_name = output.name();
 		info!("Output destroyed, named: {}", output_name);
-		comfy_kernel.output_data_map.remove(&output_name);
-		debug!(
-			"Removed OutputData from data_map! Nb of total entries: {}",
-			comfy_kernel.output_data_map.len()
-		);
+		comfy_kernel.forget_disabled_output(&output_name);
+		comfy_kernel.remove_output_data(&output_name);
+		comfy_kernel.defer(DeferredAction::PublishOutputStates);
 		()
 	}
 }
@@ -198,31 +661,53 @@ impl WLROutputManagerHandler for OutputManagerHandler {
 			use output_handle as output;
 
 			let comfy_kernel: &mut ComfyKernel = compositor.data.downcast_mut().unwrap();
-			comfy_kernel.active_output_name = output.name();
-			let xcursor_manager = &mut comfy_kernel.xcursor_manager;
-			// TODO use output config if present instead of auto
-			let output_layout_handle = &mut comfy_kernel.output_layout_handle;
-			let cursor_handle = &mut comfy_kernel.cursor_handle;
-			let output_data_map = &mut comfy_kernel.output_data_map;
-
-			use cursor_handle as cursor;
-			use output_layout_handle as output_layout;
-
-			output_layout.add_auto(output);
-			cursor.attach_output_layout(output_layout);
-			xcursor_manager.load(output.scale());
-			xcursor_manager.set_cursor_image("left_ptr".to_string(), cursor);
-			let (x, y) = cursor.coords();
-			// https://en.wikipedia.org/wiki/Mouse_warping
-			cursor.warp(None, x, y);
-
-			println!("New output detected, named: {}", output.name());
-			let (x, y) = output.layout_space_pos();
-			let (width, height) = output.effective_resolution();
-			output_data_map.insert(
-				output.name(),
-				OutputData::new(Area::new(Origin::new(x, y), Size::new(width, height))),
-			);
+			let output_name = output.name();
+			let output_config_option = comfy_kernel.config.global.outputs.get(&output_name).cloned();
+			let output_is_enabled = output_config_option
+				.as_ref()
+				.map(|output_config| output_config.enabled)
+				.unwrap_or(true);
+			if !output_is_enabled {
+				// ? A disabled output stays out of the layout until a config reload enables it
+				info!("New output detected, named: {} (disabled by the config)", output_name);
+				output.enable(false);
+				comfy_kernel.disabled_output_handles.push(output_handle.clone());
+			} else {
+				// ? The mode set by the configuration would emit the mode event of the output inside this handler
+				if output_config_option.is_some() {
+					comfy_kernel.apply_output_configs();
+				}
+				comfy_kernel.active_output_name = output_name.clone();
+				{
+					let xcursor_manager = &mut comfy_kernel.xcursor_manager;
+					let output_layout_handle = &mut comfy_kernel.output_layout_handle;
+					let cursor_handle = &mut comfy_kernel.cursor_handle;
+					let output_data_map = &mut comfy_kernel.output_data_map;
+
+					use cursor_handle as cursor;
+					use output_layout_handle as output_layout;
+
+					place_output(output_layout, output, output_config_option.as_ref());
+					cursor.attach_output_layout(output_layout);
+					xcursor_manager.load(output.scale());
+					xcursor_manager.set_cursor_image("left_ptr".to_string(), cursor);
+					let (x, y) = cursor.coords();
+					// https://en.wikipedia.org/wiki/Mouse_warping
+					cursor.warp(None, x, y);
+
+					println!("New output detected, named: {}", output_name);
+					let (x, y) = output.layout_space_pos();
+					let (width, height) = output.effective_resolution();
+					output_data_map.insert(
+						output_name.clone(),
+						OutputData::new(Area::new(Origin::new(x, y), Size::new(width, height))),
+					);
+				}
+				comfy_kernel.restore_parked_workspaces(&output_name);
+				comfy_kernel.emit_event(IpcEvent::OutputAdded { output: output_name });
+			}
+			// ? The output is borrowed by this handler, it can't be listed with the others yet
+			comfy_kernel.defer(DeferredAction::PublishOutputStates);
 		}
 		Some(result)
 	}
@@ -0,0 +1,282 @@
+use std::ffi::CStr;
+use std::os::raw::c_void;
+
+use wayland_sys::server::signal::wl_signal_add;
+use wayland_sys::server::{wl_display, wl_listener};
+use wlroots::wlroots_sys::{
+	wl_list, wl_output_transform, wlr_output, wlr_output_configuration_head_v1, wlr_output_configuration_head_v1_create,
+	wlr_output_configuration_v1, wlr_output_configuration_v1_create, wlr_output_configuration_v1_destroy,
+	wlr_output_configuration_v1_send_failed, wlr_output_configuration_v1_send_succeeded, wlr_output_manager_v1,
+	wlr_output_manager_v1_create, wlr_output_manager_v1_set_configuration,
+};
+
+use compositor::decoration::new_listener;
+use compositor::output::{apply_output_change, output_mode_index, OutputChange, OutputModeChange};
+use compositor::ComfyKernel;
+use ipc::with_comfy_kernel;
+
+/*
+.##...##...####...##..##...####....####...######..##...##..######..##..##..######.
+.###.###..##..##..###.##..##..##..##......##......###.###..##......###.##....##...
+.##.#.##..######..##.###..######..##.###..####....##.#.##..####....##.###....##...
+.##...##..##..##..##..##..##..##..##..##..##......##...##..##......##..##....##...
+.##...##..##..##..##..##..##..##...####...######..##...##..######..##..##....##...
+..................................................................................
+*/
+
+/// State of an output requested by a client, read from a head of it's configuration.
+pub struct RequestedOutputState {
+	pub enabled: bool,
+	/// Position of the top left corner of the output in the layout.
+	pub position: (i32, i32),
+	pub output_change: OutputChange,
+}
+
+/// Lets external tools (kanshi, wlr-randr, etc...) configure the outputs through wlr-output-management-unstable-v1.
+/// The configurations applied by the clients last until the sections of the outputs are applied again. (Example: on a
+/// config reload)
+pub struct OutputManagement {
+	manager: *mut wlr_output_manager_v1,
+	/// Registered for as long as the manager exists, which is destroyed with the display.
+	_apply_listener: Box<wl_listener>,
+	_test_listener: Box<wl_listener>,
+}
+
+impl OutputManagement {
+	/// Advertises the protocol on the display, then starts listening for the configurations sent by the clients.
+	pub fn create(display: *mut wl_display) -> Result<Self, String> {
+		let manager = unsafe { wlr_output_manager_v1_create(display as *mut _) };
+		if manager.is_null() {
+			return Err("Could not create the output manager".to_string());
+		}
+		let mut apply_listener = new_listener(handle_apply_configuration);
+		let mut test_listener = new_listener(handle_test_configuration);
+		unsafe {
+			wl_signal_add(
+				&mut (*manager).events.apply as *mut _ as *mut _,
+				apply_listener.as_mut(),
+			);
+			wl_signal_add(&mut (*manager).events.test as *mut _ as *mut _, test_listener.as_mut());
+		}
+		Ok(OutputManagement {
+			manager,
+			_apply_listener: apply_listener,
+			_test_listener: test_listener,
+		})
+	}
+}
+
+/// Sends the current state of the outputs to the clients, the enabled ones with their position in the layout.
+#[wlroots_dehandle(output_layout, output, disabled_output)]
+pub fn publish_output_states(comfy_kernel: &mut ComfyKernel) {
+	let manager = match comfy_kernel.output_management {
+		Some(ref output_management) => output_management.manager,
+		None => return,
+	};
+	let configuration = unsafe { wlr_output_configuration_v1_create() };
+	if configuration.is_null() {
+		error!("Could not create the configuration of the outputs");
+		return;
+	}
+	{
+		let output_layout_handle = comfy_kernel.output_layout_handle.clone();
+		use output_layout_handle as output_layout;
+		for (output_handle, _) in output_layout.outputs() {
+			use output_handle as output;
+			let (x, y) = output.layout_space_pos();
+			unsafe {
+				let head = wlr_output_configuration_head_v1_create(configuration, output.as_ptr());
+				if !head.is_null() {
+					(*head).state.x = x;
+					(*head).state.y = y;
+				}
+			}
+		}
+	}
+	for disabled_output_handle in comfy_kernel.disabled_output_handles.iter() {
+		use disabled_output_handle as disabled_output;
+		unsafe { wlr_output_configuration_head_v1_create(configuration, disabled_output.as_ptr()) };
+	}
+	// ? The manager takes ownership of the configuration
+	unsafe { wlr_output_manager_v1_set_configuration(manager, configuration) };
+}
+
+/// Returns the current state of each output, enabled or not, in the form of a requested state so it can be applied
+/// again.
+#[wlroots_dehandle(output_layout, output, disabled_output)]
+fn current_output_states(comfy_kernel: &mut ComfyKernel) -> Vec<RequestedOutputState> {
+	let mut current_output_states = Vec::new();
+	let output_layout_handle = comfy_kernel.output_layout_handle.clone();
+	use output_layout_handle as output_layout;
+	for (output_handle, _) in output_layout.outputs() {
+		use output_handle as output;
+		let position = output.layout_space_pos();
+		current_output_states.push(unsafe { current_output_state(output.as_ptr(), true, position) });
+	}
+	for disabled_output_handle in comfy_kernel.disabled_output_handles.iter() {
+		use disabled_output_handle as disabled_output;
+		current_output_states.push(unsafe { current_output_state(disabled_output.as_ptr(), false, (0, 0)) });
+	}
+	current_output_states
+}
+
+/// Returns the current mode, scale and transform of the output. A mode it doesn't list is kept as a custom one.
+unsafe fn current_output_state(output: *mut wlr_output, enabled: bool, position: (i32, i32)) -> RequestedOutputState {
+	let mode = if !enabled {
+		None
+	} else {
+		match output_mode_index(output, (*output).current_mode) {
+			Some(index) => Some(OutputModeChange::Listed(index)),
+			None if (*output).width > 0 && (*output).height > 0 => Some(OutputModeChange::Custom {
+				width: (*output).width,
+				height: (*output).height,
+				refresh: (*output).refresh,
+			}),
+			None => None,
+		}
+	};
+	RequestedOutputState {
+		enabled,
+		position,
+		output_change: OutputChange {
+			output,
+			output_name: advertised_output_name(output),
+			mode,
+			scale: Some((*output).scale),
+			transform: Some((*output).transform),
+		},
+	}
+}
+
+/// Reads the state requested for each output by the heads of the configuration. Fails if an enabled output has no
+/// usable mode, scale or transform, nothing is applied in that case. Whether an output accepts a custom mode is only
+/// known once it's set.
+unsafe fn read_requested_output_states(
+	configuration: *mut wlr_output_configuration_v1,
+) -> Result<Vec<RequestedOutputState>, String> {
+	let mut requested_output_states = Vec::new();
+	let heads: *mut wl_list = &mut (*configuration).heads;
+	let mut link = (*heads).next;
+	while link != heads {
+		let head = container_of!(link, wlr_output_configuration_head_v1, link);
+		link = (*link).next;
+
+		let state = &(*head).state;
+		let output_name = advertised_output_name(state.output);
+		let mode = if !state.enabled {
+			None
+		} else if !state.mode.is_null() {
+			let index = output_mode_index(state.output, state.mode)
+				.ok_or_else(|| format!("The requested mode isn't one of the modes of {}", output_name))?;
+			Some(OutputModeChange::Listed(index))
+		} else if state.custom_mode.width > 0 && state.custom_mode.height > 0 && state.custom_mode.refresh >= 0 {
+			Some(OutputModeChange::Custom {
+				width: state.custom_mode.width,
+				height: state.custom_mode.height,
+				refresh: state.custom_mode.refresh,
+			})
+		} else {
+			return Err(format!("No mode was requested for {}", output_name));
+		};
+		if state.enabled && !(state.scale > 0.0 && state.scale.is_finite()) {
+			return Err(format!(
+				"The scale of {} must be positive, got {}",
+				output_name, state.scale
+			));
+		}
+		if state.enabled && state.transform as u32 > wl_output_transform::WL_OUTPUT_TRANSFORM_FLIPPED_270 as u32 {
+			return Err(format!(
+				"The transform {} requested for {} doesn't exist",
+				state.transform as u32, output_name
+			));
+		}
+		requested_output_states.push(RequestedOutputState {
+			enabled: state.enabled,
+			position: (state.x, state.y),
+			output_change: OutputChange {
+				output: state.output,
+				output_name,
+				mode,
+				scale: Some(state.scale as f32),
+				transform: Some(state.transform),
+			},
+		});
+	}
+	Ok(requested_output_states)
+}
+
+/// Applies the requested states, or none of them: the outputs go back to their previous state if the mode of one of
+/// them could not be set.
+unsafe fn apply_requested_output_states(requested_output_states: &[RequestedOutputState]) -> Result<(), String> {
+	let mut previous_output_states = Vec::new();
+	with_comfy_kernel(|comfy_kernel| previous_output_states = current_output_states(comfy_kernel));
+	if let Err(e) = set_output_states(requested_output_states) {
+		if let Err(restore_error) = set_output_states(&previous_output_states) {
+			error!("Could not restore the previous state of the outputs: {}", restore_error);
+		}
+		return Err(e);
+	}
+	Ok(())
+}
+
+/// Sets the states in steps like a deferred action, the kernel is only borrowed before and after setting the modes of
+/// the outputs. Returns an error naming the outputs whose mode could not be set.
+unsafe fn set_output_states(requested_output_states: &[RequestedOutputState]) -> Result<(), String> {
+	let mut output_changes = Vec::new();
+	with_comfy_kernel(|comfy_kernel| {
+		output_changes = comfy_kernel.prepare_requested_output_changes(requested_output_states)
+	});
+	let mut failed_output_names = Vec::new();
+	for output_change in output_changes.iter() {
+		if !apply_output_change(output_change) {
+			failed_output_names.push(output_change.output_name.clone());
+		}
+	}
+	with_comfy_kernel(|comfy_kernel| comfy_kernel.finish_output_changes(&output_changes));
+	if failed_output_names.is_empty() {
+		Ok(())
+	} else {
+		Err(format!("Could not set the mode of {}", failed_output_names.join(", ")))
+	}
+}
+
+/// Returns the name of the output, as advertised to the clients.
+unsafe fn advertised_output_name(output: *mut wlr_output) -> String {
+	CStr::from_ptr((*output).name.as_ptr()).to_string_lossy().into_owned()
+}
+
+/*
+.##..##...####...##..##..#####...##......######..#####....####..
+.##..##..##..##..###.##..##..##..##......##......##..##..##.....
+.######..######..##.###..##..##..##......####....#####....####..
+.##..##..##..##..##..##..##..##..##......##......##..##......##.
+.##..##..##..##..##..##..#####...######..######..##..##...####..
+................................................................
+*/
+
+/// Called when a client asks for a configuration of the outputs to be applied.
+unsafe extern "C" fn handle_apply_configuration(_listener: *mut wl_listener, data: *mut c_void) {
+	let configuration = data as *mut wlr_output_configuration_v1;
+	let result = read_requested_output_states(configuration)
+		.and_then(|requested_output_states| apply_requested_output_states(&requested_output_states));
+	answer_configuration(configuration, result);
+}
+
+/// Called when a client asks if a configuration of the outputs could be applied, without applying it.
+unsafe extern "C" fn handle_test_configuration(_listener: *mut wl_listener, data: *mut c_void) {
+	let configuration = data as *mut wlr_output_configuration_v1;
+	let result = read_requested_output_states(configuration).map(|_| ());
+	answer_configuration(configuration, result);
+}
+
+/// Tells the client whether it's configuration succeeded, then destroys it.
+unsafe fn answer_configuration(configuration: *mut wlr_output_configuration_v1, result: Result<(), String>) {
+	match result {
+		Ok(()) => wlr_output_configuration_v1_send_succeeded(configuration),
+		Err(e) => {
+			warn!("Rejected the configuration of the outputs: {}", e);
+			wlr_output_configuration_v1_send_failed(configuration);
+		}
+	}
+	wlr_output_configuration_v1_destroy(configuration);
+}
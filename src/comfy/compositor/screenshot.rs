@@ -0,0 +1,219 @@
+use std::os::raw::c_void;
+use std::path::PathBuf;
+use std::thread;
+
+use image::{self, ColorType};
+use wlroots::wlroots_sys::{
+	wl_display, wlr_backend_get_renderer, wlr_output, wlr_renderer_read_pixels, wlr_screencopy_manager_v1_create,
+};
+use wlroots::{Area, Origin, Size, TextureFormat};
+
+/// The rows were read from the bottom to the top.
+const READ_PIXELS_Y_INVERT: u32 = 0x01;
+
+/// Number of bytes of a pixel read back from the renderer. (RGBA)
+const BYTES_PER_PIXEL: usize = 4;
+
+/*
+..####....####...#####...######..######..##..##...####....####...#####...##..##.
+.##......##..##..##..##..##......##......###.##..##..##..##..##..##..##...####..
+..####...##......#####...####....####....##.###..##......##..##..#####.....##...
+.....##..##..##..##..##..##......##......##..##..##..##..##..##..##........##...
+..####....####...##..##..######..######..##..##...####....####...##........##...
+................................................................................
+*/
+
+/// Advertises wlr-screencopy on the display so external tools (grim, wf-recorder, etc...) can capture the outputs.
+/// The copies are then handled by wlroots on it's own.
+pub fn create_screencopy_manager(display: *mut wl_display) -> Result<(), String> {
+	let screencopy_manager = unsafe { wlr_screencopy_manager_v1_create(display as *mut _) };
+	if screencopy_manager.is_null() {
+		Err("Could not create the screencopy manager".to_string())
+	} else {
+		Ok(())
+	}
+}
+
+/*
+..####....####...#####...######..######..##..##...####...##..##...####...######.
+.##......##..##..##..##..##......##......###.##..##......##..##..##..##....##...
+..####...##......#####...####....####....##.###...####...######..##..##....##...
+.....##..##..##..##..##..##......##......##..##......##..##..##..##..##....##...
+..####....####...##..##..######..######..##..##...####...##..##...####.....##...
+................................................................................
+*/
+
+/// Part of the screen captured by the `screenshot` command.
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ScreenshotTarget {
+	/// The whole active output.
+	Output,
+	/// The active window, borders included.
+	Window,
+	/// An area provided with the command. (Example: `screenshot region 10,20 640x480 shot.png`)
+	Region,
+}
+
+/// Screenshot waiting for the next frame of it's output to be rendered.
+pub struct PendingScreenshot {
+	/// Captured area, in layout coordinates.
+	pub area: Area,
+	pub path: PathBuf,
+}
+
+impl PendingScreenshot {
+	/// Reads back the captured area from the frame rendered on the output, then encodes it as a PNG in the background.
+	/// Must be called before the frame is swapped.
+	pub unsafe fn capture(self, output: *mut wlr_output, output_area: &Area, scale: f32) -> Result<(), String> {
+		let frame_width = (*output).width as u32;
+		let frame_height = (*output).height as u32;
+		let crop_area = scale_area(&relative_area(&self.area, output_area), scale);
+		let crop_area = match clip_area(&crop_area, frame_width as i32, frame_height as i32) {
+			Some(crop_area) => crop_area,
+			None => return Err("The screenshot area is outside of the output".to_string()),
+		};
+
+		// ? The whole frame is read since the origin of the rows depends on the renderer
+		let renderer = wlr_backend_get_renderer((*output).backend);
+		let stride = frame_width * BYTES_PER_PIXEL as u32;
+		let mut pixels = vec![0u8; (stride * frame_height) as usize];
+		let mut flags = 0;
+		let pixels_were_read = wlr_renderer_read_pixels(
+			renderer,
+			TextureFormat::ABGR8888.into(),
+			&mut flags,
+			stride,
+			frame_width,
+			frame_height,
+			0,
+			0,
+			0,
+			0,
+			pixels.as_mut_ptr() as *mut c_void,
+		);
+		if !pixels_were_read {
+			return Err("Could not read the pixels of the frame".to_string());
+		}
+
+		let path = self.path;
+		thread::spawn(move || {
+			let is_y_inverted = flags & READ_PIXELS_Y_INVERT != 0;
+			let cropped_pixels = crop_pixels(
+				&pixels,
+				frame_width as usize,
+				frame_height as usize,
+				is_y_inverted,
+				&crop_area,
+			);
+			match image::save_buffer(
+				&path,
+				&cropped_pixels,
+				crop_area.size.width as u32,
+				crop_area.size.height as u32,
+				ColorType::RGBA(8),
+			) {
+				Ok(_) => info!("Saved a screenshot to {}", path.display()),
+				Err(e) => error!("Could not save the screenshot to {}: {}", path.display(), e),
+			}
+		});
+		Ok(())
+	}
+}
+
+/// Parses the area of a region screenshot, written as `<x>,<y> <width>x<height>` like the output of slurp.
+pub fn parse_region(origin_str: &str, size_str: &str) -> Result<Area, String> {
+	let parse_pair = |pair_str: &str, separator: char| -> Option<(i32, i32)> {
+		let mut values = pair_str.splitn(2, separator).map(|value_str| value_str.parse::<i32>());
+		match (values.next(), values.next()) {
+			(Some(Ok(first)), Some(Ok(second))) => Some((first, second)),
+			_ => None,
+		}
+	};
+	let (x, y) = parse_pair(origin_str, ',').ok_or(format!("Invalid region origin '{}'", origin_str))?;
+	let (width, height) = parse_pair(size_str, 'x').ok_or(format!("Invalid region size '{}'", size_str))?;
+	if width <= 0 || height <= 0 {
+		return Err(format!("The region must not be empty, got {}", size_str));
+	}
+	Ok(Area::new(Origin::new(x, y), Size::new(width, height)))
+}
+
+/// Returns the area relative to the origin of the provided bounds.
+fn relative_area(area: &Area, bounds: &Area) -> Area {
+	Area::new(
+		Origin::new(area.origin.x - bounds.origin.x, area.origin.y - bounds.origin.y),
+		area.size,
+	)
+}
+
+/// Converts an area from layout coordinates to the pixels of an output with the provided scale.
+fn scale_area(area: &Area, scale: f32) -> Area {
+	Area::new(
+		Origin::new(
+			(area.origin.x as f32 * scale) as i32,
+			(area.origin.y as f32 * scale) as i32,
+		),
+		Size::new(
+			(area.size.width as f32 * scale) as i32,
+			(area.size.height as f32 * scale) as i32,
+		),
+	)
+}
+
+/// Returns the part of the area inside a frame of the provided dimensions, if any.
+fn clip_area(area: &Area, frame_width: i32, frame_height: i32) -> Option<Area> {
+	let left = area.origin.x.max(0);
+	let top = area.origin.y.max(0);
+	let right = (area.origin.x + area.size.width).min(frame_width);
+	let bottom = (area.origin.y + area.size.height).min(frame_height);
+	if left >= right || top >= bottom {
+		return None;
+	}
+	Some(Area::new(Origin::new(left, top), Size::new(right - left, bottom - top)))
+}
+
+/// Copies the rows of the cropped area from the frame, from the top to the bottom. The alpha channel is made opaque
+/// since the frame is not meant to be blended with anything.
+fn crop_pixels(
+	frame_pixels: &[u8],
+	frame_width: usize,
+	frame_height: usize,
+	is_y_inverted: bool,
+	crop_area: &Area,
+) -> Vec<u8> {
+	let frame_stride = frame_width * BYTES_PER_PIXEL;
+	let row_start = crop_area.origin.x as usize * BYTES_PER_PIXEL;
+	let row_end = row_start + crop_area.size.width as usize * BYTES_PER_PIXEL;
+	let mut cropped_pixels = Vec::with_capacity((row_end - row_start) * crop_area.size.height as usize);
+	for y in crop_area.origin.y as usize..(crop_area.origin.y + crop_area.size.height) as usize {
+		let frame_row = if is_y_inverted { frame_height - 1 - y } else { y };
+		let row = &frame_pixels[frame_row * frame_stride..(frame_row + 1) * frame_stride];
+		cropped_pixels.extend_from_slice(&row[row_start..row_end]);
+	}
+	for pixel in cropped_pixels.chunks_mut(BYTES_PER_PIXEL) {
+		pixel[3] = std::u8::MAX;
+	}
+	cropped_pixels
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_region_reads_the_slurp_format() {
+		let area = parse_region("10,-20", "640x480").unwrap();
+		assert_eq!(area, Area::new(Origin::new(10, -20), Size::new(640, 480)));
+		assert!(parse_region("10", "640x480").is_err());
+		assert!(parse_region("10,20", "0x480").is_err());
+	}
+
+	#[test]
+	fn crop_pixels_flips_inverted_frames() {
+		// ? 1x3 frame stored from the bottom to the top, each pixel holds it's row number from the top
+		let frame_pixels = vec![2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+		let crop_area = Area::new(Origin::new(0, 0), Size::new(1, 2));
+		let cropped_pixels = crop_pixels(&frame_pixels, 1, 3, true, &crop_area);
+		assert_eq!(cropped_pixels, vec![0, 0, 0, 255, 1, 0, 0, 255]);
+	}
+}
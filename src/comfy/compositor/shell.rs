@@ -1,12 +1,21 @@
+use wlroots::xdg_shell_events::{MoveEvent as WLRXdgMoveEvent, ResizeEvent as WLRXdgResizeEvent};
+use wlroots::xdg_shell_v6_events::{MoveEvent as WLRXdgV6MoveEvent, ResizeEvent as WLRXdgV6ResizeEvent};
+use wlroots::xwayland_events::{
+	ConfigureEvent as WLRXWaylandConfigureEvent, MoveEvent as WLRXWaylandMoveEvent, ResizeEvent as WLRXWaylandResizeEvent,
+};
 use wlroots::{
-	CompositorHandle as WLRCompositorHandle, SurfaceHandler as WLRSurfaceHandler,
-	XdgV6ShellHandler as WLRXdgV6ShellHandler, XdgV6ShellManagerHandler as WLRXdgV6ShellManagerHandler,
-	XdgV6ShellSurfaceHandle as WLRXdgV6ShellSurfaceHandle,
+	Area, CompositorHandle as WLRCompositorHandle, Origin, Size, SurfaceHandle as WLRSurfaceHandle,
+	SurfaceHandler as WLRSurfaceHandler, XWaylandManagerHandler as WLRXWaylandManagerHandler,
+	XWaylandSurfaceHandle as WLRXWaylandSurfaceHandle, XWaylandSurfaceHandler as WLRXWaylandSurfaceHandler,
+	XdgShellHandler as WLRXdgShellHandler, XdgShellManagerHandler as WLRXdgShellManagerHandler,
+	XdgShellSurfaceHandle as WLRXdgShellSurfaceHandle, XdgV6ShellHandler as WLRXdgV6ShellHandler,
+	XdgV6ShellManagerHandler as WLRXdgV6ShellManagerHandler, XdgV6ShellSurfaceHandle as WLRXdgV6ShellSurfaceHandle,
 };
 
+use common::ipc::IpcEvent;
+use compositor::shell_handle::ShellHandle;
 use compositor::surface::SurfaceHandler;
 use compositor::ComfyKernel;
-use utils::handle_helper::shell_handle_helper;
 
 /*
 ..####...##..##..######..##......##.....
@@ -17,17 +26,83 @@ use utils::handle_helper::shell_handle_helper;
 ........................................
 */
 
+// Both shell protocols are handled the same way once their handle is wrapped inside a `ShellHandle`.
+
+/// Adds the shell as a new window of the active workspace if it's a top level.
+fn on_new_shell(comfy_kernel: &mut ComfyKernel, shell_handle: ShellHandle) {
+	// ? We only add the shell handle as a window if it's a top level
+	if shell_handle.is_top_level() {
+		comfy_kernel.add_window_to_active_workspace(shell_handle.clone());
+		if let Some(mapped_window) = comfy_kernel.find_window(&shell_handle) {
+			comfy_kernel.emit_event(IpcEvent::WindowMapped {
+				window: mapped_window.get_info(),
+			});
+		}
+	}
+}
+
+/// Removes the window of the shell if it's a top level.
+fn on_shell_destroyed(comfy_kernel: &mut ComfyKernel, shell_handle: ShellHandle) {
+	if shell_handle.is_top_level() {
+		let destroyed_window_option = comfy_kernel.find_window(&shell_handle);
+		if comfy_kernel.find_and_remove_window(&shell_handle) {
+			if let Some(destroyed_window) = destroyed_window_option {
+				comfy_kernel.emit_event(IpcEvent::WindowDestroyed {
+					window: destroyed_window.get_info(),
+				});
+			}
+		}
+	}
+}
+
+/// Starts an interactive move or resize (from the provided edges) requested by the client.
+fn on_grab_request(comfy_kernel: &mut ComfyKernel, shell_handle: ShellHandle, resize_edges_option: Option<u32>) {
+	// ? A client requests a grab in response to a click, without a held button nothing would ever end it
+	let button = match comfy_kernel.held_buttons.last() {
+		Some(button) => *button,
+		None => {
+			debug!("Ignored a move or resize request made without any pointer button held");
+			return;
+		}
+	};
+	// ? Only floating windows can be moved by their client, the layout decides where the others go
+	if comfy_kernel.pointer_grab.is_none()
+		&& !comfy_kernel.start_floating_window_grab(&shell_handle, resize_edges_option, button, true)
+	{
+		debug!("Ignored the move or resize request of a tiled window");
+	}
+}
+
 pub struct XdgV6ShellHandler;
 impl WLRXdgV6ShellHandler for XdgV6ShellHandler {
 	#[wlroots_dehandle(compositor)]
-	fn destroyed(&mut self, compositor_handle: WLRCompositorHandle, shell_handle: WLRXdgV6ShellSurfaceHandle) {
-		// ? We only add the shell handle as a window if it's a top level
+	fn move_request(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: WLRSurfaceHandle,
+		shell_handle: WLRXdgV6ShellSurfaceHandle,
+		_: &WLRXdgV6MoveEvent,
+	) {
 		use compositor_handle as compositor;
+		on_grab_request(compositor.into(), ShellHandle::XdgV6(shell_handle), None);
+	}
 
-		if shell_handle_helper::is_top_level(&shell_handle) {
-			let comfy_kernel: &mut ComfyKernel = compositor.into();
-			comfy_kernel.find_and_remove_window(&shell_handle);
-		}
+	#[wlroots_dehandle(compositor)]
+	fn resize_request(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: WLRSurfaceHandle,
+		shell_handle: WLRXdgV6ShellSurfaceHandle,
+		event: &WLRXdgV6ResizeEvent,
+	) {
+		use compositor_handle as compositor;
+		on_grab_request(compositor.into(), ShellHandle::XdgV6(shell_handle), Some(event.edges()));
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn destroyed(&mut self, compositor_handle: WLRCompositorHandle, shell_handle: WLRXdgV6ShellSurfaceHandle) {
+		use compositor_handle as compositor;
+		on_shell_destroyed(compositor.into(), ShellHandle::XdgV6(shell_handle));
 	}
 }
 
@@ -39,12 +114,206 @@ impl WLRXdgV6ShellManagerHandler for XdgV6ShellManagerHandler {
 		compositor_handle: WLRCompositorHandle,
 		shell_handle: WLRXdgV6ShellSurfaceHandle,
 	) -> (Option<Box<WLRXdgV6ShellHandler>>, Option<Box<WLRSurfaceHandler>>) {
-		// ? We only add the shell handle as a window if it's a top level
-		if shell_handle_helper::is_top_level(&shell_handle) {
-			use compositor_handle as compositor;
-			let comfy_kernel: &mut ComfyKernel = compositor.into();
-			comfy_kernel.add_window_to_active_workspace(shell_handle);
-		}
+		use compositor_handle as compositor;
+		on_new_shell(compositor.into(), ShellHandle::XdgV6(shell_handle));
 		(Some(Box::new(XdgV6ShellHandler)), Some(Box::new(SurfaceHandler)))
 	}
 }
+
+pub struct XdgShellHandler;
+impl WLRXdgShellHandler for XdgShellHandler {
+	#[wlroots_dehandle(compositor)]
+	fn move_request(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: WLRSurfaceHandle,
+		shell_handle: WLRXdgShellSurfaceHandle,
+		_: &WLRXdgMoveEvent,
+	) {
+		use compositor_handle as compositor;
+		on_grab_request(compositor.into(), ShellHandle::Xdg(shell_handle), None);
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn resize_request(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: WLRSurfaceHandle,
+		shell_handle: WLRXdgShellSurfaceHandle,
+		event: &WLRXdgResizeEvent,
+	) {
+		use compositor_handle as compositor;
+		on_grab_request(compositor.into(), ShellHandle::Xdg(shell_handle), Some(event.edges()));
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn destroyed(&mut self, compositor_handle: WLRCompositorHandle, shell_handle: WLRXdgShellSurfaceHandle) {
+		use compositor_handle as compositor;
+		on_shell_destroyed(compositor.into(), ShellHandle::Xdg(shell_handle));
+	}
+}
+
+pub struct XdgShellManagerHandler;
+impl WLRXdgShellManagerHandler for XdgShellManagerHandler {
+	#[wlroots_dehandle(compositor)]
+	fn new_surface(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		shell_handle: WLRXdgShellSurfaceHandle,
+	) -> (Option<Box<WLRXdgShellHandler>>, Option<Box<WLRSurfaceHandler>>) {
+		use compositor_handle as compositor;
+		on_new_shell(compositor.into(), ShellHandle::Xdg(shell_handle));
+		(Some(Box::new(XdgShellHandler)), Some(Box::new(SurfaceHandler)))
+	}
+}
+
+// X11 surfaces exist before being displayed, so they become windows when they are mapped instead of when they are
+// created. Unmanaged surfaces (override redirect) are kept outside of the workspaces.
+
+/// Returns the area requested by the client of the X11 surface.
+fn xwayland_surface_area(shell_handle: &WLRXWaylandSurfaceHandle) -> Option<Area> {
+	shell_handle
+		.run(|shell| {
+			let (x, y) = shell.coords();
+			let (width, height) = shell.dimensions();
+			Area::new(
+				Origin::new(i32::from(x), i32::from(y)),
+				Size::new(i32::from(width), i32::from(height)),
+			)
+		}).ok()
+}
+
+pub struct XWaylandSurfaceHandler;
+impl WLRXWaylandSurfaceHandler for XWaylandSurfaceHandler {
+	#[wlroots_dehandle(compositor)]
+	fn on_map(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: Option<WLRSurfaceHandle>,
+		shell_handle: WLRXWaylandSurfaceHandle,
+	) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		let wrapped_shell_handle = ShellHandle::XWayland(shell_handle.clone());
+		if wrapped_shell_handle.is_top_level() {
+			// ? A window shown again keeps it's id
+			match comfy_kernel.take_unmapped_window(&wrapped_shell_handle) {
+				Some(window) => match comfy_kernel.insert_window_in_active_workspace(window.clone()) {
+					Ok(()) => comfy_kernel.emit_event(IpcEvent::WindowMapped {
+						window: window.get_info(),
+					}),
+					Err(e) => error!("{}", e),
+				},
+				None => on_new_shell(comfy_kernel, wrapped_shell_handle),
+			}
+		} else if let Some(area) = xwayland_surface_area(&shell_handle) {
+			comfy_kernel.add_unmanaged_window(wrapped_shell_handle, area);
+		}
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn on_unmap(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: Option<WLRSurfaceHandle>,
+		shell_handle: WLRXWaylandSurfaceHandle,
+	) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		let wrapped_shell_handle = ShellHandle::XWayland(shell_handle);
+		comfy_kernel.remove_unmanaged_window(&wrapped_shell_handle);
+		// ? The window is only hidden, it's destroyed along with it's surface
+		if wrapped_shell_handle.is_top_level() {
+			comfy_kernel.unmap_window(&wrapped_shell_handle);
+		}
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn destroyed(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: Option<WLRSurfaceHandle>,
+		shell_handle: WLRXWaylandSurfaceHandle,
+	) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		let wrapped_shell_handle = ShellHandle::XWayland(shell_handle);
+		comfy_kernel.remove_unmanaged_window(&wrapped_shell_handle);
+		match comfy_kernel.take_unmapped_window(&wrapped_shell_handle) {
+			Some(unmapped_window) => comfy_kernel.emit_event(IpcEvent::WindowDestroyed {
+				window: unmapped_window.get_info(),
+			}),
+			None => on_shell_destroyed(comfy_kernel, wrapped_shell_handle),
+		}
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn on_request_configure(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: Option<WLRSurfaceHandle>,
+		shell_handle: WLRXWaylandSurfaceHandle,
+		event: &WLRXWaylandConfigureEvent,
+	) {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		let wrapped_shell_handle = ShellHandle::XWayland(shell_handle);
+
+		// ? Windows keep the area given by the compositor, the others are placed where their client wants
+		let area = Area::new(
+			Origin::new(i32::from(event.x()), i32::from(event.y())),
+			Size::new(i32::from(event.width()), i32::from(event.height())),
+		);
+		if !comfy_kernel.move_unmanaged_window(&wrapped_shell_handle, area) {
+			if let Some(window) = comfy_kernel.find_window(&wrapped_shell_handle) {
+				window.apply_resize();
+				return;
+			}
+		}
+		wrapped_shell_handle.set_geometry(
+			area.origin.x,
+			area.origin.y,
+			area.size.width as u32,
+			area.size.height as u32,
+		);
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn on_move(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: Option<WLRSurfaceHandle>,
+		shell_handle: WLRXWaylandSurfaceHandle,
+		_: &WLRXWaylandMoveEvent,
+	) {
+		use compositor_handle as compositor;
+		on_grab_request(compositor.into(), ShellHandle::XWayland(shell_handle), None);
+	}
+
+	#[wlroots_dehandle(compositor)]
+	fn on_resize(
+		&mut self,
+		compositor_handle: WLRCompositorHandle,
+		_: Option<WLRSurfaceHandle>,
+		shell_handle: WLRXWaylandSurfaceHandle,
+		event: &WLRXWaylandResizeEvent,
+	) {
+		use compositor_handle as compositor;
+		on_grab_request(
+			compositor.into(),
+			ShellHandle::XWayland(shell_handle),
+			Some(event.edges()),
+		);
+	}
+}
+
+pub struct XWaylandManagerHandler;
+impl WLRXWaylandManagerHandler for XWaylandManagerHandler {
+	fn new_surface(
+		&mut self,
+		_: WLRCompositorHandle,
+		_: WLRXWaylandSurfaceHandle,
+	) -> Option<Box<WLRXWaylandSurfaceHandler>> {
+		Some(Box::new(XWaylandSurfaceHandler))
+	}
+}
@@ -0,0 +1,193 @@
+use wlroots::wlroots_sys::wlr_xdg_surface;
+use wlroots::{
+	SurfaceHandle as WLRSurfaceHandle, XWaylandSurfaceHandle as WLRXWaylandSurfaceHandle,
+	XdgShellState as WLRXdgShellState, XdgShellSurfaceHandle as WLRXdgShellSurfaceHandle,
+	XdgV6ShellState as WLRXdgV6ShellState, XdgV6ShellSurfaceHandle as WLRXdgV6ShellSurfaceHandle,
+};
+
+/*
+..####...##..##..######..##......##.....
+.##......##..##..##......##......##.....
+..####...######..####....##......##.....
+.....##..##..##..##......##......##.....
+..####...##..##..######..######..######.
+........................................
+.##..##...####...##..##..#####...##......######.
+.##..##..##..##..###.##..##..##..##......##.....
+.######..######..##.###..##..##..##......####...
+.##..##..##..##..##..##..##..##..##......##.....
+.##..##..##..##..##..##..#####...######..######.
+................................................
+*/
+
+/// Runs the first expression on the top level of an xdg shell, or the second one on an X11 surface. Both expressions
+/// must have the same type. Evaluates to `None` if the xdg shell is not a top level or if the shell was destroyed.
+macro_rules! with_top_level {
+	($shell_handle:expr, $top_level:ident => $body:expr, $xwayland_surface:ident => $xwayland_body:expr) => {
+		match $shell_handle {
+			ShellHandle::XdgV6(ref shell_handle) => shell_handle
+				.run(|shell| match shell.state() {
+					Some(&mut WLRXdgV6ShellState::TopLevel(ref mut $top_level)) => Some($body),
+					_ => None,
+				}).unwrap_or(None),
+			ShellHandle::Xdg(ref shell_handle) => shell_handle
+				.run(|shell| match shell.state() {
+					Some(&mut WLRXdgShellState::TopLevel(ref mut $top_level)) => Some($body),
+					_ => None,
+				}).unwrap_or(None),
+			ShellHandle::XWayland(ref shell_handle) => shell_handle.run(|$xwayland_surface| $xwayland_body).ok(),
+		}
+	};
+}
+
+/// Handle to the shell of a window, for each of the shell protocols supported by the compositor.
+#[derive(Clone, PartialEq)]
+pub enum ShellHandle {
+	/// Shell of a client speaking xdg-shell-unstable-v6.
+	XdgV6(WLRXdgV6ShellSurfaceHandle),
+	/// Shell of a client speaking the stable xdg-shell. (`xdg_wm_base`)
+	Xdg(WLRXdgShellSurfaceHandle),
+	/// Surface of an X11 client, through XWayland.
+	XWayland(WLRXWaylandSurfaceHandle),
+}
+
+impl ShellHandle {
+	/// Returns true if the shell is a top level. Popups and unmanaged X11 surfaces (menus, tooltips, etc...) are not
+	/// managed as windows.
+	pub fn is_top_level(&self) -> bool {
+		with_top_level!(*self, _top_level => true, xwayland_surface => !xwayland_surface.override_redirect())
+			.unwrap_or(false)
+	}
+
+	/// Returns the handle of the main surface of the shell.
+	pub fn surface(&self) -> Option<WLRSurfaceHandle> {
+		match *self {
+			ShellHandle::XdgV6(ref shell_handle) => shell_handle.run(|shell| shell.surface()).ok(),
+			ShellHandle::Xdg(ref shell_handle) => shell_handle.run(|shell| shell.surface()).ok(),
+			// ? An X11 surface only has a wayland surface once it is mapped
+			ShellHandle::XWayland(ref shell_handle) => shell_handle.run(|shell| shell.surface()).unwrap_or(None),
+		}
+	}
+
+	/// Returns the surface of the shell found at the provided coordinates (relative to the shell) and the coordinates
+	/// relative to that surface.
+	pub fn surface_at(&self, x: f64, y: f64) -> Option<(WLRSurfaceHandle, f64, f64)> {
+		let mut surface_x = 0.0;
+		let mut surface_y = 0.0;
+		let surface_handle_option = match *self {
+			ShellHandle::XdgV6(ref shell_handle) => shell_handle
+				.run(|shell| shell.surface_at(x, y, &mut surface_x, &mut surface_y))
+				.unwrap_or(None),
+			ShellHandle::Xdg(ref shell_handle) => shell_handle
+				.run(|shell| shell.surface_at(x, y, &mut surface_x, &mut surface_y))
+				.unwrap_or(None),
+			// ? X11 surfaces don't have subsurfaces, only the bounds of the main surface are checked
+			ShellHandle::XWayland(_) => {
+				surface_x = x;
+				surface_y = y;
+				self.surface().filter(|surface_handle| {
+					surface_handle
+						.run(|surface| {
+							let (width, height) = surface.current_state().size();
+							x >= 0.0 && y >= 0.0 && x < f64::from(width) && y < f64::from(height)
+						}).unwrap_or(false)
+				})
+			}
+		};
+		surface_handle_option.map(|surface_handle| (surface_handle, surface_x, surface_y))
+	}
+
+	/// Checks that the client is still responsive.
+	pub fn ping(&self) {
+		match *self {
+			ShellHandle::XdgV6(ref shell_handle) => shell_handle.run(|shell| shell.ping()).ok(),
+			ShellHandle::Xdg(ref shell_handle) => shell_handle.run(|shell| shell.ping()).ok(),
+			ShellHandle::XWayland(_) => None,
+		};
+	}
+
+	/// Applies the provided function on all surfaces of the shell, with their coordinates relative to the shell.
+	pub fn for_each_surface(&self, f: &mut FnMut(WLRSurfaceHandle, i32, i32)) {
+		match *self {
+			ShellHandle::XdgV6(ref shell_handle) => shell_handle.run(|shell| shell.for_each_surface(f)).ok(),
+			ShellHandle::Xdg(ref shell_handle) => shell_handle.run(|shell| shell.for_each_surface(f)).ok(),
+			ShellHandle::XWayland(_) => self.surface().map(|surface_handle| f(surface_handle, 0, 0)),
+		};
+	}
+
+	/// Sends the geometry of the shell to it's client. Only X11 clients use the position, the others are placed by the
+	/// compositor without knowing it.
+	pub fn set_geometry(&self, x: i32, y: i32, width: u32, height: u32) {
+		with_top_level!(
+			*self,
+			top_level => {
+				top_level.set_size(width, height);
+			},
+			xwayland_surface => xwayland_surface.configure(x as i16, y as i16, width as u16, height as u16)
+		);
+	}
+
+	pub fn set_activated(&self, is_activated: bool) {
+		with_top_level!(
+			*self,
+			top_level => {
+				top_level.set_activated(is_activated);
+			},
+			xwayland_surface => xwayland_surface.activate(is_activated)
+		);
+	}
+
+	pub fn set_maximized(&self, is_maximized: bool) {
+		with_top_level!(
+			*self,
+			top_level => {
+				top_level.set_maximized(is_maximized);
+			},
+			xwayland_surface => xwayland_surface.set_maximized(is_maximized)
+		);
+	}
+
+	pub fn set_fullscreen(&self, is_fullscreen: bool) {
+		with_top_level!(
+			*self,
+			top_level => {
+				top_level.set_fullscreen(is_fullscreen);
+			},
+			xwayland_surface => xwayland_surface.set_fullscreen(is_fullscreen)
+		);
+	}
+
+	/// Sends a close request to the client.
+	pub fn close(&self) {
+		with_top_level!(
+			*self,
+			top_level => {
+				top_level.close();
+			},
+			xwayland_surface => xwayland_surface.close()
+		);
+	}
+
+	/// Returns the title and the app id of the top level, both are empty if the client didn't set them.
+	/// The class of an X11 window is used as it's app id.
+	pub fn title_and_app_id(&self) -> (String, String) {
+		with_top_level!(
+			*self,
+			top_level => (top_level.title(), top_level.app_id()),
+			xwayland_surface => (
+				xwayland_surface.title().unwrap_or_default(),
+				xwayland_surface.class().unwrap_or_default()
+			)
+		)
+		.unwrap_or_else(|| (String::new(), String::new()))
+	}
+
+	/// Returns the raw xdg surface of a shell speaking the stable xdg-shell, the only protocol whose windows can
+	/// negotiate their decorations through xdg-decoration.
+	pub fn xdg_surface_ptr(&self) -> Option<*mut wlr_xdg_surface> {
+		match *self {
+			ShellHandle::Xdg(ref shell_handle) => Some(unsafe { shell_handle.as_ptr() }),
+			_ => None,
+		}
+	}
+}
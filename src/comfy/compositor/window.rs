@@ -1,11 +1,16 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::ipc::WindowInfo;
+use compositor::shell_handle::ShellHandle;
+use config::animations::{AnimationEvent, Animations};
+use ipc::area_info;
 use layout::LayoutDirection;
-use utils::animation::Animation;
 use utils::area_animation::AreaAnimation;
 use utils::handle_helper::surface_helper;
+use utils::time::duration_to_millis;
 use wlroots::utils::current_time;
 use wlroots::{
 	project_box as wlr_project_box, Area, Origin, Renderer as WLRRenderer, Size, SurfaceHandle as WLRSurfaceHandle,
-	XdgV6ShellState as WLRXdgV6ShellState, XdgV6ShellSurfaceHandle as WLRXdgV6ShellSurfaceHandle,
 };
 
 /*
@@ -17,58 +22,71 @@ use wlroots::{
 ..................................................................................
 */
 
+/// Source of the identifiers given to the windows.
+static NEXT_WINDOW_ID: AtomicUsize = AtomicUsize::new(1);
+
 #[derive(Clone)]
 pub struct Window {
-	pub shell_handle: WLRXdgV6ShellSurfaceHandle,
+	pub id: usize,
+	pub shell_handle: ShellHandle,
 	pub area: Area,
 	pub is_fullscreen: bool,
+	/// The client draws it's own decorations, negotiated through xdg-decoration, so the borders are not rendered.
+	pub has_client_side_decorations: bool,
 	current_area_animation: Option<AreaAnimation>,
 	border_size: u8,
+	/// Settings of the animations of the window, from the theme.
+	pub animations: Animations,
 }
 
 impl Window {
-	pub fn new(shell_handle: WLRXdgV6ShellSurfaceHandle, area: Area, border_size: u8) -> Self {
+	pub fn new(shell_handle: ShellHandle, area: Area, border_size: u8) -> Self {
 		Window {
+			id: NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed),
 			shell_handle,
 			area,
 			is_fullscreen: false,
+			has_client_side_decorations: false,
 			current_area_animation: None,
 			border_size,
+			animations: Animations::new(),
 		}
 	}
 
+	/// Renders the top level surface of the window on the output whose top left corner is at the provided origin.
 	pub fn render_top_level_surface(
 		&self,
 		renderer: &mut WLRRenderer,
+		output_origin: Origin,
 		clear_color: &[f32; 4],
 		cursor_orientation: Option<&LayoutDirection>,
 		cursor_color: Option<&[f32; 4]>,
 	) {
-		let window_area = &self.area;
-		self
-			.shell_handle
-			.run(|shell| {
-				self.render_surface(
-					renderer,
-					&shell.surface(),
-					window_area,
-					0,
-					0,
-					clear_color,
-					cursor_orientation,
-					cursor_color,
-				)
-			}).unwrap();
+		let window_area = self.area_on_output(output_origin);
+		if let Some(surface_handle) = self.shell_handle.surface() {
+			self.render_surface(
+				renderer,
+				&surface_handle,
+				&window_area,
+				0,
+				0,
+				clear_color,
+				cursor_orientation,
+				cursor_color,
+			)
+		}
 	}
 
+	/// Renders all the surfaces of the window on the output whose top left corner is at the provided origin.
 	pub fn render_all_surfaces(
 		&self,
 		renderer: &mut WLRRenderer,
+		output_origin: Origin,
 		clear_color: &[f32; 4],
 		cursor_orientation: Option<&LayoutDirection>,
 		cursor_color: Option<&[f32; 4]>,
 	) {
-		let window_area = &self.area;
+		let window_area = self.area_on_output(output_origin);
 		self.for_each_surface(&mut |surface_handle: WLRSurfaceHandle, sx, sy| {
 			self.render_surface(
 				renderer,
@@ -83,6 +101,15 @@ impl Window {
 		});
 	}
 
+	/// Returns the area of the window relative to the output whose top left corner is at the provided origin, the
+	/// renderer of an output draws in it's own coordinates while the windows are placed in layout coordinates.
+	fn area_on_output(&self, output_origin: Origin) -> Area {
+		Area::new(
+			Origin::new(self.area.origin.x - output_origin.x, self.area.origin.y - output_origin.y),
+			self.area.size,
+		)
+	}
+
 	/// Renders the provided surface using the provided renderer. Also, renders the window borders and the cursor
 	/// indicator
 	#[wlroots_dehandle(surface)]
@@ -109,8 +136,8 @@ impl Window {
 			surface_height * renderer.output.scale() as i32,
 		);
 
-		// Render the borders only when we have a top level window that is not fullscreen.
-		if surface_helper::is_top_level(&surface) && !self.is_fullscreen {
+		// Render the borders only when we have a top level window that is not fullscreen nor decorated by it's client.
+		if surface_helper::is_top_level(&surface) && !self.is_fullscreen && !self.has_client_side_decorations {
 			self.render_borders(
 				renderer,
 				window_area,
@@ -136,10 +163,10 @@ impl Window {
 			if surface_helper::is_top_level(surface) {
 				// We apply the scissor inside the borders
 				let scissor_area = Area::new(
-					Origin::new(self.area.origin.x + border_offset, self.area.origin.y + border_offset),
+					Origin::new(window_area.origin.x + border_offset, window_area.origin.y + border_offset),
 					Size::new(
-						self.area.size.width - total_border_offset,
-						self.area.size.height - total_border_offset,
+						window_area.size.width - total_border_offset,
+						window_area.size.height - total_border_offset,
 					),
 				);
 				renderer.render_scissor(scissor_area);
@@ -204,7 +231,7 @@ impl Window {
 	}
 
 	pub fn get_border_size(&self) -> i32 {
-		if !self.is_fullscreen {
+		if !self.is_fullscreen && !self.has_client_side_decorations {
 			self.border_size as i32
 		} else {
 			0
@@ -221,36 +248,27 @@ impl Window {
 
 	pub fn toggle_fullscreen(&mut self, is_fullscreen: bool) {
 		self.is_fullscreen = is_fullscreen;
-		self
-			.shell_handle
-			.run(|shell| {
-				if let Some(&mut WLRXdgV6ShellState::TopLevel(ref mut toplevel)) = shell.state() {
-					toplevel.set_fullscreen(is_fullscreen);
-				}
-			}).unwrap();
+		self.shell_handle.set_fullscreen(is_fullscreen);
 	}
 
 	/// Sets the top level shell as maximized.
 	pub fn set_maximized(&mut self) {
-		self
-			.shell_handle
-			.run(|shell| {
-				if let Some(&mut WLRXdgV6ShellState::TopLevel(ref mut toplevel)) = shell.state() {
-					toplevel.set_maximized(true);
-				}
-			}).unwrap();
+		self.shell_handle.set_maximized(true);
 	}
 
-	/// Sets the current animation from the current direction to a provided direction.
-	/// Default animation options are:
-	/// - 200ms duration
-	/// - Ease-in-out-circ animation
-	pub fn start_animation(&mut self, destination: Area) {
+	/// Sets the current animation from the current area to a provided area, with the settings of the provided event.
+	/// The window is resized right away if the animation of the event is disabled.
+	pub fn start_animation(&mut self, destination: Area, event: AnimationEvent) {
+		let settings = self.animations.settings_for(event).clone();
+		if !settings.enabled || duration_to_millis(&settings.duration) == 0 {
+			self.resize(destination);
+			return;
+		}
 		self.current_area_animation = Some(AreaAnimation::new(
 			self.area.clone(),
 			destination,
-			200,
-			Animation::EaseInOutCirc,
+			settings.duration,
+			settings.curve,
 		));
 	}
 
@@ -283,40 +301,43 @@ impl Window {
 	}
 
 	/// Applies the area to the top level shell.
-	fn apply_resize(&mut self) {
-		self
-			.shell_handle
-			.run(|shell| {
-				if let Some(&mut WLRXdgV6ShellState::TopLevel(ref mut toplevel)) = shell.state() {
-					let mut total_border_size = self.get_total_border_size() as u32;
-
-					// This is an edge case where if we spawn too many windows the border would create a negative
-					// window size.
-					if total_border_size > self.area.size.width as u32 || total_border_size > self.area.size.height as u32 {
-						total_border_size = 0;
-					}
-					toplevel.set_size(
-						self.area.size.width as u32 - total_border_size,
-						self.area.size.height as u32 - total_border_size,
-					);
-				}
-			}).unwrap();
+	pub fn apply_resize(&self) {
+		let mut total_border_size = self.get_total_border_size() as u32;
+
+		// This is an edge case where if we spawn too many windows the border would create a negative
+		// window size.
+		if total_border_size > self.area.size.width as u32 || total_border_size > self.area.size.height as u32 {
+			total_border_size = 0;
+		}
+		let border_size = total_border_size as i32 / 2;
+		self.shell_handle.set_geometry(
+			self.area.origin.x + border_size,
+			self.area.origin.y + border_size,
+			self.area.size.width as u32 - total_border_size,
+			self.area.size.height as u32 - total_border_size,
+		);
 	}
 
 	/// Send a close request to the top level shell.
 	pub fn close(&self) {
-		self
-			.shell_handle
-			.run(|shell| {
-				if let Some(&mut WLRXdgV6ShellState::TopLevel(ref mut toplevel)) = shell.state() {
-					toplevel.close();
-				}
-			}).unwrap();
+		self.shell_handle.close();
 	}
 
 	/// Applies the provided function on all surfaces of the shell.
 	pub fn for_each_surface(&self, f: &mut FnMut(WLRSurfaceHandle, i32, i32)) {
-		self.shell_handle.run(|shell| shell.for_each_surface(f)).unwrap();
+		self.shell_handle.for_each_surface(f);
+	}
+
+	/// Returns a description of the window, used to answer IPC queries.
+	pub fn get_info(&self) -> WindowInfo {
+		let (title, app_id) = self.shell_handle.title_and_app_id();
+		WindowInfo {
+			id: self.id,
+			title,
+			app_id,
+			area: area_info(&self.area),
+			is_fullscreen: self.is_fullscreen,
+		}
 	}
 
 	/// Convert the given output-related coordinates into window-related coordinates.
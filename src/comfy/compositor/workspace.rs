@@ -1,6 +1,9 @@
-use wlroots::Area;
+use wlroots::{Area, Origin, Size};
 
-use layout::Layout;
+use compositor::shell_handle::ShellHandle;
+use compositor::window::Window;
+use config::animations::AnimationEvent;
+use layout::{Layout, LayoutDirection};
 
 /*
 .##...##...####...#####...##..##...####...#####....####....####...######.
@@ -12,13 +15,187 @@ use layout::Layout;
 */
 
 pub struct Workspace {
+	pub number: u32,
 	pub window_layout: Layout,
+	/// Windows displayed above the layout with their own free area, ordered from the bottom to the top.
+	pub floating_windows: Vec<Window>,
+	/// If `true`, the active window of the workspace is the top floating window instead of the active one of the layout.
+	pub floating_window_is_active: bool,
 }
 
 impl Workspace {
-	pub fn new(output_area: Area) -> Self {
+	pub fn new(number: u32, output_area: Area) -> Self {
 		Workspace {
+			number,
 			window_layout: Layout::new(output_area),
+			floating_windows: Vec::new(),
+			floating_window_is_active: false,
 		}
 	}
+
+	/// Returns `true` if the workspace doesn't contain any window.
+	pub fn is_empty(&self) -> bool {
+		self.window_layout.is_empty() && self.floating_windows.is_empty()
+	}
+
+	/// Returns the number of windows contained in the workspace, tiled or floating.
+	pub fn nb_windows(&self) -> usize {
+		self.window_layout.nb_windows() + self.floating_windows.len()
+	}
+
+	/// Returns true if the provided shell is contained inside the layout or the floating layer.
+	pub fn contains_shell_handle(&self, shell_handle: &ShellHandle) -> bool {
+		self.window_layout.contains_shell_handle(shell_handle) || self.index_of_floating_window(shell_handle).is_some()
+	}
+
+	/// Returns the position of the floating window bound to the provided shell handle in the floating layer.
+	fn index_of_floating_window(&self, shell_handle: &ShellHandle) -> Option<usize> {
+		self
+			.floating_windows
+			.iter()
+			.position(|window| window.shell_handle == *shell_handle)
+	}
+
+	/// Returns the window bound to the provided shell handle, tiled or floating.
+	pub fn get_window_from_shell_handle(&self, shell_handle: &ShellHandle) -> Option<&Window> {
+		self
+			.window_layout
+			.get_window_from_shell_handle(shell_handle)
+			.or_else(|| self.get_floating_window(shell_handle))
+	}
+
+	/// Returns the floating window bound to the provided shell handle.
+	pub fn get_floating_window(&self, shell_handle: &ShellHandle) -> Option<&Window> {
+		self
+			.floating_windows
+			.iter()
+			.find(|window| window.shell_handle == *shell_handle)
+	}
+
+	/// Returns a mutable reference to the floating window bound to the provided shell handle.
+	pub fn get_floating_window_mut(&mut self, shell_handle: &ShellHandle) -> Option<&mut Window> {
+		self
+			.floating_windows
+			.iter_mut()
+			.find(|window| window.shell_handle == *shell_handle)
+	}
+
+	/// Returns a clone of the active window, which is the top floating window if the floating layer has the focus.
+	pub fn get_active_window(&self) -> Option<Window> {
+		if self.floating_window_is_active {
+			self.floating_windows.last().cloned()
+		} else {
+			self.window_layout.get_active_window()
+		}
+	}
+
+	/// Returns the shell handle of the active window, see `get_active_window`.
+	pub fn get_active_shell_handle(&self) -> Option<ShellHandle> {
+		if self.floating_window_is_active {
+			self.floating_windows.last().map(|window| window.shell_handle.clone())
+		} else {
+			self.window_layout.get_active_shell_handle()
+		}
+	}
+
+	/// Sets the window bound to the provided shell handle as the active one.
+	/// A floating window is also raised above the other floating windows.
+	pub fn set_as_last_activated(&mut self, shell_handle: &ShellHandle) {
+		if let Some(index_of_floating_window) = self.index_of_floating_window(shell_handle) {
+			let floating_window = self.floating_windows.remove(index_of_floating_window);
+			self.floating_windows.push(floating_window);
+			self.floating_window_is_active = true;
+		} else if self.window_layout.contains_shell_handle(shell_handle) {
+			self.window_layout.set_as_last_activated(shell_handle);
+			self.floating_window_is_active = false;
+		}
+	}
+
+	/// Removes the window bound to the provided shell handle from the floating layer or the layout.
+	/// Returns the removed window.
+	pub fn remove_window_from_shell_handle(&mut self, shell_handle: &ShellHandle) -> Result<Window, String> {
+		if let Some(index_of_floating_window) = self.index_of_floating_window(shell_handle) {
+			let removed_window = self.floating_windows.remove(index_of_floating_window);
+			if self.floating_windows.is_empty() {
+				self.floating_window_is_active = false;
+			}
+			return Ok(removed_window);
+		}
+		self.window_layout.remove_window_from_shell_handle(shell_handle, true)
+	}
+
+	/// Adds the window on top of the floating layer and gives it the focus.
+	/// Windows coming from the layout are centered over it, with half of it's size.
+	pub fn add_floating_window(&mut self, mut window: Window) {
+		if window.is_fullscreen {
+			window.toggle_fullscreen(false);
+		}
+		if let Some(layout_area) = self.window_layout.area() {
+			let size = Size::new(layout_area.size.width / 2, layout_area.size.height / 2);
+			let origin = Origin::new(
+				layout_area.origin.x + (layout_area.size.width - size.width) / 2,
+				layout_area.origin.y + (layout_area.size.height - size.height) / 2,
+			);
+			window.start_animation(Area::new(origin, size), AnimationEvent::WindowMove);
+		}
+		self.floating_windows.push(window);
+		self.floating_window_is_active = true;
+	}
+
+	/// Moves every window of the provided workspace into this one (Example: the output of the workspace was unplugged).
+	/// Floating windows stay floating but are centered over the layout. The active window stays the same, unless this
+	/// workspace was empty in which case the active window of the provided workspace is kept.
+	pub fn take_windows_from(&mut self, mut workspace: Workspace, direction: &LayoutDirection) {
+		let was_empty = self.is_empty();
+		let active_shell_handle_option = workspace.get_active_shell_handle();
+		for window in workspace.window_layout.take_all_windows() {
+			if let Err(e) = self.window_layout.add_window(window, direction, false, true) {
+				error!("Could not move a window to the workspace {}: {}", self.number, e);
+			}
+		}
+		let floating_window_was_active = self.floating_window_is_active;
+		for window in workspace.floating_windows.drain(..) {
+			self.add_floating_window(window);
+		}
+		self.floating_window_is_active = floating_window_was_active;
+		if was_empty {
+			if let Some(active_shell_handle) = active_shell_handle_option {
+				self.set_as_last_activated(&active_shell_handle);
+			}
+		}
+	}
+
+	/// Moves the active window from the layout to the floating layer, or from the floating layer back to the layout.
+	pub fn toggle_active_window_floating(&mut self, direction: &LayoutDirection) -> Result<(), String> {
+		let active_shell_handle = match self.get_active_shell_handle() {
+			Some(active_shell_handle) => active_shell_handle,
+			None => return Err("No active window found".to_string()),
+		};
+		let window_was_floating = self.floating_window_is_active;
+		let window = self.remove_window_from_shell_handle(&active_shell_handle)?;
+		if window_was_floating {
+			self.floating_window_is_active = false;
+			self.window_layout.add_window(window, direction, true, true)
+		} else {
+			self.add_floating_window(window);
+			Ok(())
+		}
+	}
+
+	/// Returns a clone of the window found at the provided coordinates, floating windows are checked first.
+	pub fn find_window_at(&mut self, x: f64, y: f64) -> Option<Window> {
+		self
+			.find_floating_window_at(x, y)
+			.or_else(|| self.window_layout.find_window_at(x, y))
+	}
+
+	/// Returns a clone of the top floating window found at the provided coordinates.
+	pub fn find_floating_window_at(&self, x: f64, y: f64) -> Option<Window> {
+		self
+			.floating_windows
+			.iter()
+			.rev()
+			.find(|window| window.area.contains_point(x, y))
+			.cloned()
+	}
 }
@@ -0,0 +1,128 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use utils::animation::Animation;
+
+/// An intermediate struct used to parse the animations section of a Toml file
+#[derive(Deserialize, Debug)]
+pub struct TomlAnimations {
+	enabled: Option<bool>,
+	duration: Option<u64>,
+	curve: Option<String>,
+	window_open: Option<TomlAnimation>,
+	window_move: Option<TomlAnimation>,
+	window_resize: Option<TomlAnimation>,
+	window_fullscreen: Option<TomlAnimation>,
+}
+
+/// An intermediate struct used to parse the section of a single animation event
+#[derive(Deserialize, Debug)]
+pub struct TomlAnimation {
+	enabled: Option<bool>,
+	duration: Option<u64>,
+	curve: Option<String>,
+}
+
+/// Changes of a window which are animated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+	/// The window was added to a layout.
+	WindowOpen,
+	/// The window took another place in it's layout, or was made floating.
+	WindowMove,
+	/// The space of the window changed because of the windows around it or the size of it's output.
+	WindowResize,
+	/// The window entered or left fullscreen.
+	WindowFullscreen,
+}
+
+/// How the area of a window goes from it's current value to a new one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationSettings {
+	/// Without animation, the window is resized right away.
+	pub enabled: bool,
+	pub duration: Duration,
+	pub curve: Animation,
+}
+
+impl AnimationSettings {
+	pub fn new() -> Self {
+		AnimationSettings {
+			enabled: true,
+			duration: Duration::from_millis(200),
+			curve: Animation::EaseInOutCirc,
+		}
+	}
+
+	/// Returns a copy of the settings with the provided options applied over them.
+	fn with_overrides(
+		&self,
+		enabled: Option<bool>,
+		duration_in_millis: Option<u64>,
+		curve: Option<String>,
+	) -> Result<Self, String> {
+		let mut settings = self.clone();
+		if let Some(enabled) = enabled {
+			settings.enabled = enabled;
+		}
+		if let Some(duration_in_millis) = duration_in_millis {
+			settings.duration = Duration::from_millis(duration_in_millis);
+		}
+		if let Some(curve_str) = curve {
+			settings.curve =
+				Animation::from_str(&curve_str).map_err(|_| format!("Unknown animation curve: {}", curve_str))?;
+		}
+		Ok(settings)
+	}
+}
+
+/// Settings of the animation of each window event, from the `[animations]` section of the theme.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animations {
+	pub window_open: AnimationSettings,
+	pub window_move: AnimationSettings,
+	pub window_resize: AnimationSettings,
+	pub window_fullscreen: AnimationSettings,
+}
+
+impl Animations {
+	pub fn new() -> Self {
+		Animations {
+			window_open: AnimationSettings::new(),
+			window_move: AnimationSettings::new(),
+			window_resize: AnimationSettings::new(),
+			window_fullscreen: AnimationSettings::new(),
+		}
+	}
+
+	/// The options at the root of the section apply to every event, unless the section of the event overrides them.
+	pub fn from_toml(toml_animations: TomlAnimations) -> Result<Self, String> {
+		let default_settings = AnimationSettings::new().with_overrides(
+			toml_animations.enabled,
+			toml_animations.duration,
+			toml_animations.curve,
+		)?;
+		let settings_of_event = |toml_animation_option: Option<TomlAnimation>| match toml_animation_option {
+			Some(toml_animation) => {
+				default_settings.with_overrides(toml_animation.enabled, toml_animation.duration, toml_animation.curve)
+			}
+			None => Ok(default_settings.clone()),
+		};
+		Ok(Animations {
+			window_open: settings_of_event(toml_animations.window_open)?,
+			window_move: settings_of_event(toml_animations.window_move)?,
+			window_resize: settings_of_event(toml_animations.window_resize)?,
+			window_fullscreen: settings_of_event(toml_animations.window_fullscreen)?,
+		})
+	}
+
+	/// Returns the settings of the animation started by the provided event.
+	pub fn settings_for(&self, event: AnimationEvent) -> &AnimationSettings {
+		match event {
+			AnimationEvent::WindowOpen => &self.window_open,
+			AnimationEvent::WindowMove => &self.window_move,
+			AnimationEvent::WindowResize => &self.window_resize,
+			AnimationEvent::WindowFullscreen => &self.window_fullscreen,
+		}
+	}
+}
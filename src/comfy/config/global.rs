@@ -1,16 +1,44 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::str::FromStr;
 use toml;
 
+use config::keyboard::{KeyboardConfig, TomlKeyboard};
+use config::output::{OutputConfig, TomlOutput};
+
 /// An intermediate struct used to parse a Toml file
 #[derive(Deserialize, Debug)]
 struct TomlGlobal {
 	pointer_focus_type: Option<String>,
+	xwayland: Option<bool>,
+	decorations: Option<TomlDecorations>,
+	output: Option<HashMap<String, TomlOutput>>,
+	input: Option<TomlInput>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TomlDecorations {
+	policy: Option<String>,
+	app_ids: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TomlInput {
+	keyboard: Option<TomlKeyboard>,
 }
 
 pub struct Global {
 	pub pointer_focus_type: PointerFocusType,
+	/// Starts an XWayland server to run X11 applications. Only read at launch.
+	pub xwayland: bool,
+	pub decoration_policy: DecorationPolicy,
+	/// Decoration policies overriding the default one for the windows with a specific app id.
+	pub app_id_decoration_policies: HashMap<String, DecorationPolicy>,
+	/// Configurations of the outputs, by output name. The outputs without one are configured automatically.
+	pub outputs: HashMap<String, OutputConfig>,
+	/// Keymap applied to the keyboard when it is plugged in and when the config is reloaded.
+	pub keyboard: KeyboardConfig,
 }
 
 #[derive(PartialEq, Eq, ToString, EnumString)]
@@ -20,10 +48,25 @@ pub enum PointerFocusType {
 	OnClick,
 }
 
+/// Decides who draws the decorations of the windows negotiating them through xdg-decoration.
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum DecorationPolicy {
+	/// The compositor draws it's borders, even if the client would rather draw it's own decorations.
+	ForceServer,
+	/// The client draws it's own decorations, unless it asks for server-side ones.
+	PreferClient,
+}
+
 impl Global {
 	pub fn new() -> Self {
 		Global {
 			pointer_focus_type: PointerFocusType::OnHover,
+			xwayland: false,
+			decoration_policy: DecorationPolicy::ForceServer,
+			app_id_decoration_policies: HashMap::new(),
+			outputs: HashMap::new(),
+			keyboard: KeyboardConfig::new(),
 		}
 	}
 
@@ -56,6 +99,39 @@ impl Global {
 			}
 		}
 
+		if let Some(xwayland) = parsed_content.xwayland {
+			global.xwayland = xwayland;
+		}
+
+		if let Some(decorations) = parsed_content.decorations {
+			if let Some(policy_str) = decorations.policy {
+				global.decoration_policy =
+					DecorationPolicy::from_str(&policy_str).map_err(|_| format!("Unknown decoration policy: {}", policy_str))?;
+			}
+			for (app_id, policy_str) in decorations.app_ids.unwrap_or_default() {
+				let policy = DecorationPolicy::from_str(&policy_str)
+					.map_err(|_| format!("Unknown decoration policy for {}: {}", app_id, policy_str))?;
+				global.app_id_decoration_policies.insert(app_id, policy);
+			}
+		}
+
+		for (output_name, toml_output) in parsed_content.output.unwrap_or_default() {
+			let output_config = OutputConfig::from_toml(&output_name, toml_output)?;
+			global.outputs.insert(output_name, output_config);
+		}
+
+		if let Some(toml_keyboard) = parsed_content.input.and_then(|toml_input| toml_input.keyboard) {
+			global.keyboard = KeyboardConfig::from_toml(toml_keyboard)?;
+		}
+
 		Ok(global)
 	}
+
+	/// Returns the decoration policy of the windows with the provided app id.
+	pub fn decoration_policy_for(&self, app_id: &str) -> &DecorationPolicy {
+		self
+			.app_id_decoration_policies
+			.get(app_id)
+			.unwrap_or(&self.decoration_policy)
+	}
 }
@@ -1,28 +1,185 @@
+use common::command_type::CommandType;
 use compositor::commands::Command;
-use config::parser::convert_to_xkb_string;
+use config::mousebinding::{parse_mousebindings, MouseInput};
+use config::parser::{convert_to_xkb_sequences, convert_to_xkb_string};
 use input::keyboard::XkbKeySet;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
+use std::time::Duration;
 use toml;
 
+/// Name of the mode using the bindings of the `[keybindings]` section, active when the compositor starts.
+pub const DEFAULT_MODE: &str = "default";
+
+/// Time given to press the next keyset of a sequence, in milliseconds.
+const DEFAULT_SEQUENCE_TIMEOUT: u64 = 1000;
+
 /// An intermediate struct used to parse a Toml file
 #[derive(Deserialize, Debug)]
 struct TomlKeybindings {
 	modkey: String,
-	keybindings: HashMap<String, String>,
+	sequence_timeout: Option<u64>,
+	keybindings: HashMap<String, TomlBinding>,
+	mode: Option<HashMap<String, HashMap<String, TomlBinding>>>,
+	mousebindings: Option<HashMap<String, String>>,
+}
+
+/// An intermediate enum used to parse a binding, either it's command or a table with it's options.
+/// (Example: `"Super_L" = { command = "exec bemenu-run", on_release = true }`)
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum TomlBinding {
+	Command(String),
+	Options {
+		command: String,
+		on_release: Option<bool>,
+		passthrough: Option<bool>,
+	},
+}
+
+/// Command bound to a keyset, with the way it is triggered.
+#[derive(Clone)]
+pub struct BoundCommand {
+	pub command: Command,
+	/// The command runs when the keyset is released, if no other key was pressed in the meantime.
+	pub on_release: bool,
+	/// The key is still sent to the focused window.
+	pub passthrough: bool,
+}
+
+/// What a keyset is bound to.
+pub enum Binding {
+	Command(BoundCommand),
+	/// The keyset starts a sequence, the next keyset is looked up in these bindings.
+	Sequence(BindingTrie),
+}
+
+/// Bindings of a mode, the sequences of keysets are stored as nested bindings.
+pub struct BindingTrie {
+	bindings: HashMap<XkbKeySet, Binding>,
+}
+
+impl BindingTrie {
+	pub fn new() -> Self {
+		BindingTrie {
+			bindings: HashMap::new(),
+		}
+	}
+
+	/// Returns what the provided key_set is bound to, if anything.
+	pub fn lookup(&self, key_set: &XkbKeySet) -> Option<&Binding> {
+		self.bindings.get(key_set)
+	}
+
+	/// Returns the command bound to the provided key_set, without going through any sequence.
+	pub fn get(&self, key_set: &XkbKeySet) -> Option<&Command> {
+		match self.bindings.get(key_set) {
+			Some(Binding::Command(bound_command)) => Some(&bound_command.command),
+			_ => None,
+		}
+	}
+
+	/// Returns the bindings following the provided keysets, or `None` if they are not the start of a sequence.
+	pub fn sequence_bindings(&self, key_sets: &[XkbKeySet]) -> Option<&BindingTrie> {
+		match key_sets.split_first() {
+			Some((key_set, next_key_sets)) => match self.bindings.get(key_set) {
+				Some(Binding::Sequence(bindings)) => bindings.sequence_bindings(next_key_sets),
+				_ => None,
+			},
+			None => Some(self),
+		}
+	}
+
+	/// Returns true if the provided key_set is part of one of the keysets bound at this level, like the modifiers
+	/// pressed before the last key of a keyset.
+	pub fn has_key_set_containing(&self, key_set: &XkbKeySet) -> bool {
+		self
+			.bindings
+			.keys()
+			.any(|bound_key_set| key_set.keysyms_set.is_subset(&bound_key_set.keysyms_set))
+	}
+
+	/// Returns every command of the bindings, the ones ending the sequences included.
+	pub fn commands(&self) -> Vec<&Command> {
+		let mut commands = Vec::new();
+		for binding in self.bindings.values() {
+			match binding {
+				Binding::Command(bound_command) => commands.push(&bound_command.command),
+				Binding::Sequence(bindings) => commands.extend(bindings.commands()),
+			}
+		}
+		commands
+	}
+
+	/// Binds the command to the provided sequence of keysets, a single keyset being a sequence of one.
+	fn insert(&mut self, key_sets: &[XkbKeySet], bound_command: BoundCommand) -> Result<(), String> {
+		let (key_set, next_key_sets) = match key_sets.split_first() {
+			Some(split_key_sets) => split_key_sets,
+			None => return Err("Cannot bind an empty sequence".to_string()),
+		};
+		let binding = self
+			.bindings
+			.entry(key_set.clone())
+			.or_insert_with(|| Binding::Sequence(BindingTrie::new()));
+		if next_key_sets.is_empty() {
+			if let Binding::Sequence(ref bindings) = *binding {
+				if !bindings.bindings.is_empty() {
+					return Err("A keyset cannot both start a sequence and be bound to a command".to_string());
+				}
+			}
+			*binding = Binding::Command(bound_command);
+			Ok(())
+		} else {
+			match *binding {
+				Binding::Sequence(ref mut bindings) => bindings.insert(next_key_sets, bound_command),
+				Binding::Command(_) => Err("A keyset cannot both start a sequence and be bound to a command".to_string()),
+			}
+		}
+	}
+}
+
+/// Result of the lookup of the pressed keys in the bindings of the current mode.
+pub enum BindingMatch {
+	Command(BoundCommand),
+	/// The keys are part of a sequence, the next keyset is awaited.
+	Pending,
+	Unbound,
 }
 
 pub struct Keybindings {
 	pub modkey: Vec<XkbKeySet>,
-	pub bindings: HashMap<XkbKeySet, Command>,
+	pub bindings: BindingTrie,
+	/// Bindings of the modes declared with a `[mode.<name>]` section, by mode name.
+	pub modes: HashMap<String, BindingTrie>,
+	/// Time given to press the next keyset of a sequence before it is abandoned.
+	pub sequence_timeout: Duration,
+	/// Commands bound to the mouse inputs with the keys held, from the `[mousebindings]` section.
+	pub mousebindings: HashMap<(XkbKeySet, MouseInput), Command>,
 }
 
 impl Keybindings {
 	pub fn new() -> Self {
 		Keybindings {
 			modkey: Vec::new(),
-			bindings: HashMap::new(),
+			bindings: BindingTrie::new(),
+			modes: HashMap::new(),
+			sequence_timeout: Duration::from_millis(DEFAULT_SEQUENCE_TIMEOUT),
+			mousebindings: HashMap::new(),
+		}
+	}
+
+	/// Returns true if the provided name is the default mode or one of the declared modes.
+	pub fn has_mode(&self, mode_name: &str) -> bool {
+		mode_name == DEFAULT_MODE || self.modes.contains_key(mode_name)
+	}
+
+	/// Returns the bindings of the provided mode, the ones of the `[keybindings]` section for the default mode.
+	pub fn bindings_for_mode(&self, mode_name: &str) -> Option<&BindingTrie> {
+		if mode_name == DEFAULT_MODE {
+			Some(&self.bindings)
+		} else {
+			self.modes.get(mode_name)
 		}
 	}
 
@@ -45,9 +202,16 @@ impl Keybindings {
 	///
 	/// Will crash if no modkey are in the file or is invalid (not a valid `XkbKeySet` or is two keys).
 	///
-	/// Will crash if a `Keybinding` is the same as the `Modkey`.
+	/// Will crash if a `Keybinding` is the same as the `Modkey`, unless it is triggered on release.
+	///
+	/// Will crash if a `Keybinding` is not a valid `XkbKeySet`, or a sequence of them separated by commas.
+	///
+	/// Will crash if a `Keybinding` both starts a sequence and is bound to a command.
 	///
-	/// Will crash if a `Keybinding` is not a valid `XkbKeySet`.
+	/// Will crash if a `Mousebinding` doesn't end with a valid mouse button or scroll direction.
+	///
+	/// Will crash if a mode has no bindings, is named after the default mode or if an `enter_mode` command refers to an
+	/// unknown mode.
 	pub fn parse_config_from_toml(file_content: &str) -> Result<Self, String> {
 		let mut keybindings = Keybindings::new();
 
@@ -72,23 +236,100 @@ impl Keybindings {
 			keybindings.modkey.push(XkbKeySet::from_str(modkey_keyset_str)?);
 		}
 
-		for (keys_str, command_str) in parsed_content.keybindings.iter() {
-			let xkb_keysets_strs = convert_to_xkb_string(modkey_str, keys_str)?;
+		if let Some(sequence_timeout) = parsed_content.sequence_timeout {
+			keybindings.sequence_timeout = Duration::from_millis(sequence_timeout);
+		}
 
-			for xkb_keyset_str in xkb_keysets_strs.iter() {
-				if modkey_keyset_strs.contains(xkb_keyset_str) {
-					return Err(format!("Command set to modkey! {} = {}", keys_str, command_str));
-				}
+		keybindings.bindings = parse_bindings(modkey_str, &modkey_keyset_strs, &parsed_content.keybindings)?;
 
-				let xkb_keyset = XkbKeySet::from_str(xkb_keyset_str).unwrap();
-				if command_str.is_empty() {
-					return Err(format!("The command associated with {} is empty", &keys_str));
+		for (mode_name, bindings_strs) in parsed_content.mode.iter().flat_map(|modes| modes.iter()) {
+			if mode_name == DEFAULT_MODE {
+				return Err(format!(
+					"The bindings of the {} mode belong to the keybindings section",
+					DEFAULT_MODE
+				));
+			}
+			if bindings_strs.is_empty() {
+				return Err(format!("No bindings specified for the mode {}", mode_name));
+			}
+			let bindings = parse_bindings(modkey_str, &modkey_keyset_strs, bindings_strs)?;
+			if !bindings
+				.commands()
+				.iter()
+				.any(|command| command.command_type == CommandType::EnterMode)
+			{
+				warn!("The mode {} has no binding to leave it", mode_name);
+			}
+			keybindings.modes.insert(mode_name.clone(), bindings);
+		}
+
+		if let Some(ref mousebindings_strs) = parsed_content.mousebindings {
+			keybindings.mousebindings = parse_mousebindings(modkey_str, mousebindings_strs)?;
+		}
+
+		let mut all_commands = keybindings.bindings.commands();
+		for bindings in keybindings.modes.values() {
+			all_commands.extend(bindings.commands());
+		}
+		all_commands.extend(keybindings.mousebindings.values());
+		for command in all_commands {
+			if command.command_type != CommandType::EnterMode {
+				continue;
+			}
+			match command.args.first() {
+				Some(mode_name) if keybindings.has_mode(mode_name) => {}
+				Some(mode_name) => {
+					return Err(format!(
+						"The command enter_mode refers to an unknown mode: {}",
+						mode_name
+					))
 				}
-				let command = Command::from_str(command_str)?;
-				keybindings.bindings.insert(xkb_keyset, command);
+				None => return Err("The command enter_mode needs the name of a mode".to_string()),
 			}
 		}
 
 		Ok(keybindings)
 	}
 }
+
+/// Converts the bindings of a section to a trie of the commands bound to each sequence of `XkbKeySet`.
+fn parse_bindings(
+	modkey_str: &str,
+	modkey_keyset_strs: &[String],
+	bindings_strs: &HashMap<String, TomlBinding>,
+) -> Result<BindingTrie, String> {
+	let mut bindings = BindingTrie::new();
+	for (keys_str, toml_binding) in bindings_strs.iter() {
+		let (command_str, on_release, passthrough) = match toml_binding {
+			TomlBinding::Command(command_str) => (command_str, false, false),
+			TomlBinding::Options {
+				command,
+				on_release,
+				passthrough,
+			} => (command, on_release.unwrap_or(false), passthrough.unwrap_or(false)),
+		};
+		if command_str.is_empty() {
+			return Err(format!("The command associated with {} is empty", &keys_str));
+		}
+		let bound_command = BoundCommand {
+			command: Command::from_str(command_str)?,
+			on_release,
+			passthrough,
+		};
+
+		for xkb_sequence_strs in convert_to_xkb_sequences(modkey_str, keys_str)?.iter() {
+			let mut xkb_sequence = Vec::new();
+			for xkb_keyset_str in xkb_sequence_strs.iter() {
+				// ? Tapping the modkey alone doesn't get in the way of the other bindings
+				if modkey_keyset_strs.contains(xkb_keyset_str) && !on_release {
+					return Err(format!("Command set to modkey! {} = {}", keys_str, command_str));
+				}
+				xkb_sequence.push(XkbKeySet::from_str(xkb_keyset_str)?);
+			}
+			bindings
+				.insert(&xkb_sequence, bound_command.clone())
+				.map_err(|e| format!("{}: {}", e, keys_str))?;
+		}
+	}
+	Ok(bindings)
+}
@@ -0,0 +1,57 @@
+/// An intermediate struct used to parse the keyboard section of a Toml file
+#[derive(Deserialize, Debug)]
+pub struct TomlKeyboard {
+	rules: Option<String>,
+	model: Option<String>,
+	layout: Option<String>,
+	variant: Option<String>,
+	options: Option<String>,
+}
+
+/// Keymap of the keyboard, described by the names of it's xkb components. The empty ones use the defaults of xkb.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardConfig {
+	pub rules: String,
+	pub model: String,
+	/// Layouts separated by commas, the keyboard can switch between them. (Example: `us,ca`)
+	pub layout: String,
+	/// Variant of each layout, separated by commas as well.
+	pub variant: String,
+	/// Options separated by commas. (Example: `grp:alt_shift_toggle,caps:escape`)
+	pub options: Option<String>,
+}
+
+impl KeyboardConfig {
+	pub fn new() -> Self {
+		KeyboardConfig {
+			rules: String::new(),
+			model: String::new(),
+			layout: String::new(),
+			variant: String::new(),
+			options: None,
+		}
+	}
+
+	pub fn from_toml(toml_keyboard: TomlKeyboard) -> Result<Self, String> {
+		let keyboard_config = KeyboardConfig {
+			rules: toml_keyboard.rules.unwrap_or_default(),
+			model: toml_keyboard.model.unwrap_or_default(),
+			layout: toml_keyboard.layout.unwrap_or_default(),
+			variant: toml_keyboard.variant.unwrap_or_default(),
+			options: toml_keyboard.options,
+		};
+		let nb_variants = keyboard_config.variant.split(',').count() as u32;
+		if nb_variants > keyboard_config.nb_layouts() {
+			return Err(format!(
+				"The keyboard has more variants than layouts: {} for {}",
+				keyboard_config.variant, keyboard_config.layout
+			));
+		}
+		Ok(keyboard_config)
+	}
+
+	/// Returns the number of layouts the keyboard can switch between.
+	pub fn nb_layouts(&self) -> u32 {
+		self.layout.split(',').count() as u32
+	}
+}
@@ -1,5 +1,9 @@
+pub mod animations;
 pub mod global;
 pub mod keybinding;
+pub mod keyboard;
+pub mod mousebinding;
+pub mod output;
 pub mod parser;
 pub mod theme;
 
@@ -141,7 +145,17 @@ mod tests {
 	use super::*;
 	use common::command_type::CommandType;
 	use compositor::commands::Command;
+	use config::animations::AnimationEvent;
+	use config::global::DecorationPolicy;
+	use config::keybinding::Binding;
+	use config::keyboard::KeyboardConfig;
+	use config::mousebinding::MouseInput;
+	use config::output::{OutputModeConfig, OutputTransform};
+	use config::theme::WallpaperMode;
 	use input::keyboard::XkbKeySet;
+	use input::pointer::BTN_EXTRA;
+	use std::time::Duration;
+	use utils::animation::Animation;
 
 	#[test]
 	fn generate_valid_config() {
@@ -241,4 +255,240 @@ mod tests {
 		assert!(Keybindings::parse_config_from_toml(no_keybindings).is_err());
 		assert!(Keybindings::parse_config_from_toml(no_keybindings_section).is_err());
 	}
+
+	#[test]
+	fn generate_config_with_modes() {
+		let config = r#"modkey = "Control"
+			[keybindings]
+			"$mod+r" = "enter_mode resize"
+			[mode.resize]
+			"Left" = "shrink_active_window left"
+			"Escape" = "enter_mode default"
+		"#;
+		let keybindings = Keybindings::parse_config_from_toml(config).unwrap();
+		assert!(keybindings.has_mode(keybinding::DEFAULT_MODE));
+		assert!(keybindings.has_mode("resize"));
+		let left_keyset = XkbKeySet::from_str("Left").unwrap();
+		assert!(keybindings.bindings.get(&left_keyset).is_none());
+		let command = keybindings
+			.bindings_for_mode("resize")
+			.and_then(|bindings| bindings.get(&left_keyset))
+			.expect("The command Left should exist in the resize mode");
+		assert_eq!(command.command_type, CommandType::ShrinkActiveWindow);
+
+		let unknown_mode = r#"modkey = "Control"
+			[keybindings]
+			"$mod+r" = "enter_mode resise"
+			[mode.resize]
+			"Escape" = "enter_mode default"
+		"#;
+		let empty_mode = r#"modkey = "Control"
+			[keybindings]
+			"$mod+r" = "enter_mode resize"
+			[mode.resize]
+		"#;
+		let default_mode_section = r#"modkey = "Control"
+			[keybindings]
+			"$mod+r" = "exec weston-terminal"
+			[mode.default]
+			"Escape" = "exec weston-terminal"
+		"#;
+		assert!(Keybindings::parse_config_from_toml(unknown_mode).is_err());
+		assert!(Keybindings::parse_config_from_toml(empty_mode).is_err());
+		assert!(Keybindings::parse_config_from_toml(default_mode_section).is_err());
+	}
+
+	#[test]
+	fn generate_config_with_sequences() {
+		let config = r#"modkey = "Control"
+			sequence_timeout = 500
+			[keybindings]
+			"$mod+w, h" = "focus_output left"
+			"$mod+w, Shift+h" = "move_active_window_to_output left"
+		"#;
+		let keybindings = Keybindings::parse_config_from_toml(config).unwrap();
+		assert_eq!(keybindings.sequence_timeout, Duration::from_millis(500));
+		let first_keyset = XkbKeySet::from_str("Control_L+w").unwrap();
+		assert!(keybindings.bindings.get(&first_keyset).is_none());
+		let command = keybindings
+			.bindings
+			.sequence_bindings(&[first_keyset])
+			.and_then(|bindings| bindings.get(&XkbKeySet::from_str("h").unwrap()))
+			.expect("The sequence Control_L+w, h should exist");
+		assert_eq!(command.command_type, CommandType::FocusOutput);
+		assert_eq!(keybindings.bindings.commands().len(), 6);
+
+		let command_and_sequence = r#"modkey = "Control"
+			[keybindings]
+			"$mod+w" = "exec weston-terminal"
+			"$mod+w, h" = "focus_output left"
+		"#;
+		let empty_keyset = r#"modkey = "Control"
+			[keybindings]
+			"$mod+w, " = "focus_output left"
+		"#;
+		assert!(Keybindings::parse_config_from_toml(command_and_sequence).is_err());
+		assert!(Keybindings::parse_config_from_toml(empty_keyset).is_err());
+	}
+
+	#[test]
+	fn generate_config_with_binding_options() {
+		let config = r#"modkey = "Super_L"
+			[keybindings]
+			"$mod" = { command = "exec bemenu-run", on_release = true }
+			"F9" = { command = "exec push-to-talk", passthrough = true }
+			"$mod+Return" = "exec weston-terminal"
+		"#;
+		let keybindings = Keybindings::parse_config_from_toml(config).unwrap();
+		let bound_command_for =
+			|keyset_str: &str| match keybindings.bindings.lookup(&XkbKeySet::from_str(keyset_str).unwrap()) {
+				Some(Binding::Command(bound_command)) => bound_command.clone(),
+				_ => panic!("The command {} should exist", keyset_str),
+			};
+		let modkey_command = bound_command_for("Super_L");
+		assert!(modkey_command.on_release && !modkey_command.passthrough);
+		let passthrough_command = bound_command_for("F9");
+		assert!(passthrough_command.passthrough && !passthrough_command.on_release);
+		let command = bound_command_for("Super_L+Return");
+		assert!(!command.on_release && !command.passthrough);
+
+		let modkey_on_press = r#"modkey = "Super_L"
+			[keybindings]
+			"$mod" = { command = "exec bemenu-run", passthrough = true }
+		"#;
+		assert!(Keybindings::parse_config_from_toml(modkey_on_press).is_err());
+	}
+
+	#[test]
+	fn generate_config_with_mousebindings() {
+		let config = r#"modkey = "Control"
+			[keybindings]
+			"$mod+q" = "close_active_window"
+			[mousebindings]
+			"$mod+scroll_up" = "switch_to_workspace prev"
+			"button9" = "switch_to_workspace next"
+		"#;
+		let keybindings = Keybindings::parse_config_from_toml(config).unwrap();
+		let held_key_set = XkbKeySet::from_str("Control_R").unwrap();
+		let command = keybindings
+			.mousebindings
+			.get(&(held_key_set, MouseInput::ScrollUp))
+			.expect("The mousebinding Control_R+scroll_up should exist");
+		assert_eq!(command.command_type, CommandType::SwitchToWorkspace);
+		assert_eq!(command.args, vec!["prev".to_string()]);
+		assert!(keybindings
+			.mousebindings
+			.contains_key(&(XkbKeySet::new(), MouseInput::Button(BTN_EXTRA))));
+
+		let unknown_button = r#"modkey = "Control"
+			[keybindings]
+			"$mod+q" = "close_active_window"
+			[mousebindings]
+			"$mod+button42" = "close_active_window"
+		"#;
+		assert!(Keybindings::parse_config_from_toml(unknown_button).is_err());
+	}
+
+	#[test]
+	fn generate_global_config_with_decoration_policies() {
+		let config = r#"[decorations]
+			policy = "prefer_client"
+			[decorations.app_ids]
+			"foot" = "force_server"
+		"#;
+		let global = Global::parse_config_from_toml(config).unwrap();
+		assert_eq!(*global.decoration_policy_for("firefox"), DecorationPolicy::PreferClient);
+		assert_eq!(*global.decoration_policy_for("foot"), DecorationPolicy::ForceServer);
+
+		let invalid_policy = r#"[decorations]
+			policy = "heck"
+		"#;
+		assert!(Global::parse_config_from_toml(invalid_policy).is_err());
+	}
+
+	#[test]
+	fn generate_global_config_with_outputs() {
+		let config = r#"[output.DP-1]
+			mode = "2560x1440@143.9"
+			position = [0, 0]
+			[output.HDMI-A-1]
+			transform = "90"
+			enabled = false
+		"#;
+		let global = Global::parse_config_from_toml(config).unwrap();
+		let main_output = &global.outputs["DP-1"];
+		assert_eq!(
+			main_output.mode,
+			Some(OutputModeConfig {
+				width: 2560,
+				height: 1440,
+				refresh: Some(143_900),
+			})
+		);
+		assert_eq!(main_output.position, Some((0, 0)));
+		assert!(main_output.enabled);
+		let secondary_output = &global.outputs["HDMI-A-1"];
+		assert_eq!(secondary_output.transform, Some(OutputTransform::Rotated90));
+		assert!(!secondary_output.enabled);
+
+		let invalid_mode = r#"[output.DP-1]
+			mode = "2560by1440"
+		"#;
+		assert!(Global::parse_config_from_toml(invalid_mode).is_err());
+	}
+
+	#[test]
+	fn generate_global_config_with_keyboard() {
+		let config = r#"[input.keyboard]
+			layout = "us,ca"
+			options = "grp:alt_shift_toggle"
+		"#;
+		let global = Global::parse_config_from_toml(config).unwrap();
+		assert_eq!(global.keyboard.layout, "us,ca");
+		assert_eq!(global.keyboard.variant, "");
+		assert_eq!(global.keyboard.options, Some("grp:alt_shift_toggle".to_string()));
+		assert_eq!(global.keyboard.nb_layouts(), 2);
+		assert_eq!(Global::parse_config_from_toml("").unwrap().keyboard, KeyboardConfig::new());
+
+		let too_many_variants = r#"[input.keyboard]
+			layout = "us"
+			variant = "intl,multix"
+		"#;
+		assert!(Global::parse_config_from_toml(too_many_variants).is_err());
+	}
+
+	#[test]
+	fn generate_theme_with_output_wallpapers() {
+		let config = r#"wallpaper_path = "/default.png"
+			wallpaper_mode = "fit"
+			[output.DP-1]
+			wallpaper_path = "/ultrawide.png"
+		"#;
+		let theme = Theme::parse_theme_from_toml(config).unwrap();
+		assert_eq!(theme.wallpaper_mode, WallpaperMode::Fit);
+		assert_eq!(theme.wallpaper_path_for("DP-1").unwrap(), "/ultrawide.png");
+		assert_eq!(theme.wallpaper_path_for("HDMI-A-1").unwrap(), "/default.png");
+		assert_eq!(theme.all_wallpaper_paths().len(), 2);
+	}
+
+	#[test]
+	fn generate_theme_with_animations() {
+		let config = r#"[animations]
+			duration = 300
+			curve = "linear"
+			[animations.window_fullscreen]
+			enabled = false
+		"#;
+		let animations = Theme::parse_theme_from_toml(config).unwrap().animations;
+		let open_settings = animations.settings_for(AnimationEvent::WindowOpen);
+		assert!(open_settings.enabled);
+		assert_eq!(open_settings.duration, Duration::from_millis(300));
+		assert_eq!(open_settings.curve, Animation::Linear);
+		assert!(!animations.settings_for(AnimationEvent::WindowFullscreen).enabled);
+
+		let invalid_curve = r#"[animations]
+			curve = "bouncy"
+		"#;
+		assert!(Theme::parse_theme_from_toml(invalid_curve).is_err());
+	}
 }
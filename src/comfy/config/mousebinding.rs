@@ -0,0 +1,73 @@
+use compositor::commands::Command;
+use config::parser::convert_to_xkb_string;
+use input::keyboard::XkbKeySet;
+use input::pointer::{BTN_EXTRA, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, BTN_SIDE};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Button of the mouse or direction of it's wheel, bound to a command along with the keys held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseInput {
+	/// Code of the button. (From linux/input-event-codes.h)
+	Button(u32),
+	ScrollUp,
+	ScrollDown,
+	ScrollLeft,
+	ScrollRight,
+}
+
+impl FromStr for MouseInput {
+	type Err = String;
+
+	/// Parses `button<number>`, numbered like X11 (1: left, 2: middle, 3: right, 8: back, 9: forward), or
+	/// `scroll_<up|down|left|right>`.
+	fn from_str(input_str: &str) -> Result<Self, Self::Err> {
+		match input_str {
+			"button1" => Ok(MouseInput::Button(BTN_LEFT)),
+			"button2" => Ok(MouseInput::Button(BTN_MIDDLE)),
+			"button3" => Ok(MouseInput::Button(BTN_RIGHT)),
+			"button8" => Ok(MouseInput::Button(BTN_SIDE)),
+			"button9" => Ok(MouseInput::Button(BTN_EXTRA)),
+			"scroll_up" => Ok(MouseInput::ScrollUp),
+			"scroll_down" => Ok(MouseInput::ScrollDown),
+			"scroll_left" => Ok(MouseInput::ScrollLeft),
+			"scroll_right" => Ok(MouseInput::ScrollRight),
+			_ => Err(format!("Unknown mouse button or scroll direction: {}", input_str)),
+		}
+	}
+}
+
+/// Converts the bindings of the `[mousebindings]` section to the commands bound to each combination of held keys and
+/// mouse input. The mouse input is the last element of a binding. (Example: `"$mod+Shift+scroll_up"`)
+pub fn parse_mousebindings(
+	modkey_str: &str,
+	bindings_strs: &HashMap<String, String>,
+) -> Result<HashMap<(XkbKeySet, MouseInput), Command>, String> {
+	let mut mousebindings = HashMap::new();
+	for (keys_str, command_str) in bindings_strs.iter() {
+		if command_str.is_empty() {
+			return Err(format!("The command associated with {} is empty", &keys_str));
+		}
+		let command = Command::from_str(command_str)?;
+
+		let (held_keys_str, mouse_input_str) = match keys_str.rfind('+') {
+			Some(index) => (&keys_str[..index], &keys_str[index + 1..]),
+			None => ("", keys_str.as_str()),
+		};
+		let mouse_input = MouseInput::from_str(mouse_input_str)?;
+		let held_key_sets = if held_keys_str.is_empty() {
+			vec![XkbKeySet::new()]
+		} else {
+			let mut held_key_sets = Vec::new();
+			for xkb_keyset_str in convert_to_xkb_string(modkey_str, held_keys_str)?.iter() {
+				held_key_sets.push(XkbKeySet::from_str(xkb_keyset_str)?);
+			}
+			held_key_sets
+		};
+
+		for held_key_set in held_key_sets {
+			mousebindings.insert((held_key_set, mouse_input), command.clone());
+		}
+	}
+	Ok(mousebindings)
+}
@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+/// An intermediate struct used to parse the section of an output in a Toml file
+#[derive(Deserialize, Debug)]
+pub struct TomlOutput {
+	mode: Option<String>,
+	position: Option<[i32; 2]>,
+	scale: Option<f32>,
+	transform: Option<String>,
+	enabled: Option<bool>,
+}
+
+/// Resolution and refresh rate requested for an output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputModeConfig {
+	pub width: i32,
+	pub height: i32,
+	/// Refresh rate in mHz, the highest one available for the resolution is used if `None`.
+	pub refresh: Option<i32>,
+}
+
+impl FromStr for OutputModeConfig {
+	type Err = String;
+
+	/// Parses a mode written as `<width>x<height>[@<refresh in Hz>]`. (Example: `2560x1440@143.9`)
+	fn from_str(mode_str: &str) -> Result<Self, Self::Err> {
+		let invalid_mode_error = || format!("Invalid output mode '{}', expected <width>x<height>[@<Hz>]", mode_str);
+		let mut resolution_and_refresh = mode_str.splitn(2, '@');
+		let resolution_str = resolution_and_refresh.next().unwrap_or_default();
+		let refresh = match resolution_and_refresh.next() {
+			Some(refresh_str) => {
+				Some((refresh_str.parse::<f32>().map_err(|_| invalid_mode_error())? * 1000.0).round() as i32)
+			}
+			None => None,
+		};
+		let mut dimensions = resolution_str
+			.splitn(2, 'x')
+			.map(|dimension_str| dimension_str.parse::<i32>());
+		match (dimensions.next(), dimensions.next()) {
+			(Some(Ok(width)), Some(Ok(height))) if width > 0 && height > 0 => Ok(OutputModeConfig { width, height, refresh }),
+			_ => Err(invalid_mode_error()),
+		}
+	}
+}
+
+/// Rotation and flip applied to the content of an output.
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString)]
+pub enum OutputTransform {
+	#[strum(serialize = "normal")]
+	Normal,
+	#[strum(serialize = "90")]
+	Rotated90,
+	#[strum(serialize = "180")]
+	Rotated180,
+	#[strum(serialize = "270")]
+	Rotated270,
+	#[strum(serialize = "flipped")]
+	Flipped,
+	#[strum(serialize = "flipped_90")]
+	Flipped90,
+	#[strum(serialize = "flipped_180")]
+	Flipped180,
+	#[strum(serialize = "flipped_270")]
+	Flipped270,
+}
+
+/// Configuration of an output, from it's `[output.<name>]` section. The options left out are chosen by the compositor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputConfig {
+	pub mode: Option<OutputModeConfig>,
+	/// Position of the top left corner of the output in the layout, placed next to the other outputs if `None`.
+	pub position: Option<(i32, i32)>,
+	pub scale: Option<f32>,
+	pub transform: Option<OutputTransform>,
+	pub enabled: bool,
+}
+
+impl OutputConfig {
+	pub fn from_toml(output_name: &str, toml_output: TomlOutput) -> Result<Self, String> {
+		let mode = match toml_output.mode {
+			Some(mode_str) => Some(OutputModeConfig::from_str(&mode_str)?),
+			None => None,
+		};
+		let transform = match toml_output.transform {
+			Some(transform_str) => Some(
+				OutputTransform::from_str(&transform_str)
+					.map_err(|_| format!("Invalid transform for the output {}: {}", output_name, transform_str))?,
+			),
+			None => None,
+		};
+		if let Some(scale) = toml_output.scale {
+			if scale <= 0.0 {
+				return Err(format!(
+					"The scale of the output {} must be positive, got {}",
+					output_name, scale
+				));
+			}
+		}
+		Ok(OutputConfig {
+			mode,
+			position: toml_output.position.map(|position| (position[0], position[1])),
+			scale: toml_output.scale,
+			transform,
+			enabled: toml_output.enabled.unwrap_or(true),
+		})
+	}
+}
@@ -17,6 +17,34 @@ pub fn convert_to_xkb_string(modkey_str: &str, keyset: &str) -> Result<Vec<Strin
 	Ok(apply_casing(create_combinations_as_string(canonicalized_keys)))
 }
 
+/// Convert a sequence of keysets in Comfy's format, separated by commas, to the valid XkbKeySets of each of it's steps.
+/// Every combination of the keysets is returned as a separate sequence.
+///
+/// #Example
+/// The following value : `"Control+w, h"`
+/// returns : ```[["Control_L+w", "h"],
+///               ["Control_R+w", "h"]]```
+pub fn convert_to_xkb_sequences(modkey_str: &str, key_sequence: &str) -> Result<Vec<Vec<String>>, String> {
+	let mut sequences: Vec<Vec<String>> = vec![Vec::new()];
+	for keyset in key_sequence.split(",").map(|keyset| keyset.trim()) {
+		if keyset.is_empty() {
+			return Err(format!("Empty keyset in the sequence {}", key_sequence));
+		}
+		let xkb_keysets = convert_to_xkb_string(modkey_str, keyset)?;
+		let next_sequences = sequences
+			.iter()
+			.flat_map(|sequence| {
+				xkb_keysets.iter().map(move |xkb_keyset| {
+					let mut next_sequence = sequence.clone();
+					next_sequence.push(xkb_keyset.clone());
+					next_sequence
+				})
+			}).collect();
+		sequences = next_sequences;
+	}
+	Ok(sequences)
+}
+
 /// A function that is used to apply an uppercase on single letter when there's a "Shift" in the combination
 fn apply_casing(key_combinations: Vec<String>) -> Vec<String> {
 	let mut key_combinations_with_casing = Vec::new();
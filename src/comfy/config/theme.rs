@@ -1,6 +1,9 @@
 use common::colors::*;
+use config::animations::{Animations, TomlAnimations};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
+use std::str::FromStr;
 use toml;
 
 /// An intermediate struct used to parse a Toml file
@@ -11,6 +14,32 @@ struct TomlTheme {
 	inactive_color: Option<String>,
 	cursor_indicator_color: Option<String>,
 	wallpaper_path: Option<String>,
+	wallpaper_mode: Option<String>,
+	background_color: Option<String>,
+	output: Option<HashMap<String, TomlOutputTheme>>,
+	animations: Option<TomlAnimations>,
+}
+
+/// An intermediate struct used to parse the theme section of an output
+#[derive(Deserialize, Debug)]
+struct TomlOutputTheme {
+	wallpaper_path: Option<String>,
+}
+
+/// How the wallpaper is placed on an output which doesn't have the same resolution.
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum WallpaperMode {
+	/// Scaled to cover the whole output, the parts overflowing are cropped.
+	Fill,
+	/// Scaled to fit inside the output, the background color is displayed around it.
+	Fit,
+	/// Displayed with it's own size at the center of the output.
+	Center,
+	/// Repeated with it's own size from the top left corner of the output.
+	Tile,
+	/// Scaled to the resolution of the output, without keeping it's aspect ratio.
+	Stretch,
 }
 
 pub struct Theme {
@@ -19,6 +48,12 @@ pub struct Theme {
 	pub inactive_color: RgbaColor,
 	pub cursor_indicator_color: RgbaColor,
 	pub wallpaper_path: Option<String>,
+	pub wallpaper_mode: WallpaperMode,
+	/// Color displayed where the wallpaper doesn't cover the output, or everywhere without a wallpaper.
+	pub background_color: RgbaColor,
+	/// Wallpapers overriding the default one on specific outputs, by output name.
+	pub output_wallpaper_paths: HashMap<String, String>,
+	pub animations: Animations,
 }
 
 impl Theme {
@@ -29,6 +64,10 @@ impl Theme {
 			inactive_color: RgbaColor::new(41.0 / 255.0, 49.0 / 255.0, 46.0 / 255.0, 1.0),
 			cursor_indicator_color: RgbaColor::new(230.0 / 255.0, 52.0 / 255.0, 42.0 / 255.0, 1.0),
 			wallpaper_path: Some("/usr/share/comfywm/wallpaper.jpg".to_string()),
+			wallpaper_mode: WallpaperMode::Fill,
+			background_color: RgbaColor::new(135.0 / 255.0, 7.0 / 255.0, 52.0 / 255.0, 1.0),
+			output_wallpaper_paths: HashMap::new(),
+			animations: Animations::new(),
 		}
 	}
 
@@ -80,6 +119,50 @@ impl Theme {
 			theme.wallpaper_path = parsed_content.wallpaper_path;
 		}
 
+		if let Some(wallpaper_mode_str) = parsed_content.wallpaper_mode {
+			match WallpaperMode::from_str(&wallpaper_mode_str) {
+				Ok(wallpaper_mode) => theme.wallpaper_mode = wallpaper_mode,
+				Err(_) => warn!("Invalid wallpaper mode {}", wallpaper_mode_str),
+			}
+		}
+
+		if let Some(background_color_str) = parsed_content.background_color {
+			match HexColor::from_str(background_color_str.as_str()) {
+				Ok(background_color) => theme.background_color = background_color.to_rgba(),
+				Err(e) => warn!("Invalid background color {}", e),
+			}
+		}
+
+		for (output_name, output_theme) in parsed_content.output.unwrap_or_default() {
+			if let Some(wallpaper_path) = output_theme.wallpaper_path {
+				theme.output_wallpaper_paths.insert(output_name, wallpaper_path);
+			}
+		}
+
+		if let Some(toml_animations) = parsed_content.animations {
+			theme.animations = Animations::from_toml(toml_animations)?;
+		}
+
 		Ok(theme)
 	}
+
+	/// Returns the path of the wallpaper displayed on the provided output, if any.
+	pub fn wallpaper_path_for(&self, output_name: &str) -> Option<&String> {
+		self
+			.output_wallpaper_paths
+			.get(output_name)
+			.or_else(|| self.wallpaper_path.as_ref())
+	}
+
+	/// Returns the path of every wallpaper used by the theme, without duplicates.
+	pub fn all_wallpaper_paths(&self) -> Vec<&String> {
+		let mut wallpaper_paths: Vec<&String> = self
+			.wallpaper_path
+			.iter()
+			.chain(self.output_wallpaper_paths.values())
+			.collect();
+		wallpaper_paths.sort();
+		wallpaper_paths.dedup();
+		wallpaper_paths
+	}
 }
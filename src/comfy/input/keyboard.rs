@@ -1,6 +1,6 @@
 use wlroots::key_events::KeyEvent as WLRKeyEvent;
 use wlroots::wlroots_sys::wlr_key_state::WLR_KEY_PRESSED;
-use wlroots::xkbcommon::xkb::{keysym_from_name, KEYSYM_NO_FLAGS};
+use wlroots::xkbcommon::xkb::{self, keysym_from_name, KEYSYM_NO_FLAGS};
 use wlroots::{
 	CompositorHandle as WLRCompositorHandle, KeyboardHandle as WLRKeyboardHandle, KeyboardHandler as WLRKeyboardHandler,
 };
@@ -11,6 +11,8 @@ use std::iter::FromIterator;
 
 use compositor::commands::interpreter::CommandInterpreter;
 use compositor::ComfyKernel;
+use config::keybinding::BindingMatch;
+use config::keyboard::KeyboardConfig;
 
 /*
 .##..##..######..##..##..#####....####....####...#####...#####..
@@ -27,18 +29,50 @@ impl KeyboardHandler {
 		let key_set = XkbKeySet::from_vec_without_check(&key_event.pressed_keys());
 
 		comfy_kernel.currently_pressed_keys.set_to_union(&key_set);
+		// ? The keys bound on release were not pressed alone, the clients get the press they were kept from
+		comfy_kernel.release_command = None;
+		comfy_kernel.replay_withheld_key_press();
 
-		if let Some(command) = comfy_kernel.command_for_keyset(&comfy_kernel.currently_pressed_keys) {
-			CommandInterpreter::execute(&command, comfy_kernel);
-		} else {
-			comfy_kernel.notify_keyboard(key_event);
+		let pressed_keys = comfy_kernel.currently_pressed_keys.clone();
+		match comfy_kernel.match_keyset(&pressed_keys) {
+			BindingMatch::Command(bound_command) => {
+				if bound_command.on_release {
+					comfy_kernel.release_command = Some((pressed_keys, bound_command.command));
+					if !bound_command.passthrough {
+						comfy_kernel.withheld_key_press = Some((key_event.time_msec(), key_event.keycode()));
+					}
+				} else {
+					CommandInterpreter::execute(&bound_command.command, comfy_kernel).ok();
+				}
+				if bound_command.passthrough {
+					comfy_kernel.notify_keyboard(key_event);
+				}
+			}
+			// ? The keys of a sequence are kept from the clients until it ends
+			BindingMatch::Pending => {}
+			BindingMatch::Unbound => comfy_kernel.notify_keyboard(key_event),
 		}
 	}
 
 	fn handle_key_release(&mut self, comfy_kernel: &mut ComfyKernel, key_event: &WLRKeyEvent) {
+		if let Some((bound_key_set, command)) = comfy_kernel.release_command.take() {
+			if bound_key_set == comfy_kernel.currently_pressed_keys {
+				CommandInterpreter::execute(&command, comfy_kernel).ok();
+			}
+		}
 		let key_set = XkbKeySet::from_vec_without_check(&key_event.pressed_keys());
 		comfy_kernel.currently_pressed_keys.set_to_difference(&key_set);
-		comfy_kernel.notify_keyboard(key_event);
+
+		// ? The clients never saw the key being pressed
+		let press_was_withheld = match comfy_kernel.withheld_key_press {
+			Some((_, keycode)) => keycode == key_event.keycode(),
+			None => false,
+		};
+		if press_was_withheld {
+			comfy_kernel.withheld_key_press = None;
+		} else {
+			comfy_kernel.notify_keyboard(key_event);
+		}
 	}
 }
 
@@ -71,6 +105,24 @@ impl WLRKeyboardHandler for KeyboardHandler {
 	}
 }
 
+/// Compiles the keymap described by the names of the xkb components of the keyboard configuration.
+pub fn compile_keymap(keyboard_config: &KeyboardConfig) -> Result<xkb::Keymap, String> {
+	let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+	xkb::Keymap::new_from_names(
+		&context,
+		&keyboard_config.rules,
+		&keyboard_config.model,
+		&keyboard_config.layout,
+		&keyboard_config.variant,
+		keyboard_config.options.clone(),
+		xkb::KEYMAP_COMPILE_NO_FLAGS,
+	)
+	.ok_or(format!(
+		"Could not compile the keymap of the keyboard layout '{}'",
+		keyboard_config.layout
+	))
+}
+
 /*
 .##..##..##..##..#####...##..##..######..##..##...####...######..######.
 ..####...##.##...##..##..##.##...##.......####...##......##........##...
@@ -1,3 +1,4 @@
+use wlroots::wlroots_sys::wlr_keyboard_set_keymap;
 use wlroots::{
 	CompositorHandle as WLRCompositorHandle, InputManagerHandler as WLRInputManagerHandler,
 	KeyboardHandle as WLRKeyboardHandle, KeyboardHandler as WLRKeyboardHandler, PointerHandle as WLRPointerHandle,
@@ -25,7 +26,7 @@ pub mod seat;
 pub struct InputManagerHandler;
 impl WLRInputManagerHandler for InputManagerHandler {
 	fn pointer_added(&mut self, _: WLRCompositorHandle, _: WLRPointerHandle) -> Option<Box<WLRPointerHandler>> {
-		Some(Box::new(PointerHandler))
+		Some(Box::new(PointerHandler::default()))
 	}
 	#[wlroots_dehandle(compositor, keyboard, seat)]
 	fn keyboard_added(
@@ -35,9 +36,14 @@ impl WLRInputManagerHandler for InputManagerHandler {
 	) -> Option<Box<WLRKeyboardHandler>> {
 		{
 			use compositor_handle as compositor;
-			use keyboard_handle as keyboard;
 			let comfy_kernel: &mut ComfyKernel = compositor.into();
-			comfy_kernel.keyboard_handle = Some(keyboard.weak_reference());
+			comfy_kernel.keyboard_handle = Some(keyboard_handle.clone());
+			// ? The keymap is set before the seat sends the keyboard to the clients. It can be set right away, the
+			// handler of the keyboard isn't registered yet to receive the modifiers event it emits
+			if let Some((keyboard_ptr, keymap)) = comfy_kernel.keyboard_keymap_change() {
+				unsafe { wlr_keyboard_set_keymap(keyboard_ptr, keymap.get_raw_ptr() as *mut _) };
+			}
+			use keyboard_handle as keyboard;
 			let seat_handle = comfy_kernel.seat_handle.as_ref().unwrap();
 			use seat_handle as seat;
 			seat.set_keyboard(keyboard.input_device());
@@ -1,17 +1,224 @@
 use std::time::Duration;
 use wlroots::pointer_events::{AbsoluteMotionEvent, AxisEvent, ButtonEvent, MotionEvent};
 
+use wlroots::wlroots_sys::{wlr_axis_orientation, wlr_axis_source, wlr_button_state, wlr_edges};
 use wlroots::{
-	CompositorHandle as WLRCompositorHandle, PointerHandle as WLRPointerHandle, PointerHandler as WLRPointerHandler,
+	Area, CompositorHandle as WLRCompositorHandle, PointerHandle as WLRPointerHandle, PointerHandler as WLRPointerHandler,
 };
 
+use compositor::commands::interpreter::CommandInterpreter;
+use compositor::shell_handle::ShellHandle;
 use compositor::ComfyKernel;
 use config::global::PointerFocusType;
+use config::mousebinding::MouseInput;
+use layout::{LayoutAxis, LayoutSplit};
 
 // A pointer is a physical device (a mouse, a touchscreen, etc...) that subscribe to an event in order to communicate
 // with the compositor.
 
-pub struct PointerHandler;
+/// Codes of the buttons of the mouse. (From linux/input-event-codes.h)
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+pub const BTN_SIDE: u32 = 0x113;
+pub const BTN_EXTRA: u32 = 0x114;
+
+/// Distance scrolled by a click of a mouse wheel, continuous scrolling runs a scroll binding once per distance.
+const SCROLL_STEP_DISTANCE: f64 = 15.0;
+
+/// Floating windows can't be resized below this width or height.
+const MIN_FLOATING_WINDOW_SIZE: i32 = 50;
+
+/// Interaction which captures the pointer events, instead of sending them to the clients, until the button is released.
+#[derive(Clone)]
+pub struct PointerGrab {
+	pub kind: PointerGrabKind,
+	/// Button whose release ends the grab.
+	pub button: u32,
+	/// The grab was requested by the client, which already received the press of the button and must receive it's
+	/// release as well.
+	pub is_requested_by_client: bool,
+}
+
+/// What the pointer does during a grab.
+#[derive(Clone)]
+pub enum PointerGrabKind {
+	/// The boundary between two windows of the layout of a workspace is being dragged.
+	ResizeSplit {
+		output_name: String,
+		workspace_number: u32,
+		split: LayoutSplit,
+	},
+	/// A floating window follows the cursor.
+	MoveFloatingWindow {
+		shell_handle: ShellHandle,
+		cursor_origin: (f64, f64),
+		initial_area: Area,
+	},
+	/// The provided edges of a floating window follow the cursor. (Bitmask of `wlr_edges`)
+	ResizeFloatingWindow {
+		shell_handle: ShellHandle,
+		cursor_origin: (f64, f64),
+		initial_area: Area,
+		edges: u32,
+	},
+}
+
+#[derive(Default)]
+pub struct PointerHandler {
+	/// Distances scrolled continuously toward a bound direction, since the binding last ran.
+	vertical_scroll_distance: f64,
+	horizontal_scroll_distance: f64,
+}
+
+impl PointerHandler {
+	fn scroll_distance_mut(&mut self, orientation: wlr_axis_orientation) -> &mut f64 {
+		match orientation {
+			wlr_axis_orientation::WLR_AXIS_ORIENTATION_VERTICAL => &mut self.vertical_scroll_distance,
+			wlr_axis_orientation::WLR_AXIS_ORIENTATION_HORIZONTAL => &mut self.horizontal_scroll_distance,
+		}
+	}
+
+	/// Starts a grab if the button was pressed on something that can be dragged.
+	/// Returns true if the grab started, in which case the click must not be sent to the clients.
+	fn try_to_start_grab(&self, comfy_kernel: &mut ComfyKernel, button: u32) -> bool {
+		// ? With the modkey, the left button moves a floating window and the right one resizes it
+		if comfy_kernel.modkey_is_pressed() && (button == BTN_LEFT || button == BTN_RIGHT) {
+			if let Some(window) = comfy_kernel.find_floating_window_under_cursor() {
+				let edges_option = if button == BTN_RIGHT {
+					Some(nearest_edges(&window.area, comfy_kernel.get_cursor_coordinates()))
+				} else {
+					None
+				};
+				return comfy_kernel.start_floating_window_grab(&window.shell_handle, edges_option, button, false);
+			}
+		}
+
+		if button != BTN_LEFT {
+			return false;
+		}
+		if let Some((output_name, workspace_number, split)) = comfy_kernel.find_split_under_cursor() {
+			comfy_kernel.set_cursor_image(match split.axis {
+				LayoutAxis::Horizontal => "sb_h_double_arrow",
+				LayoutAxis::Vertical => "sb_v_double_arrow",
+			});
+			comfy_kernel.pointer_grab = Some(PointerGrab {
+				kind: PointerGrabKind::ResizeSplit {
+					output_name,
+					workspace_number,
+					split,
+				},
+				button,
+				is_requested_by_client: false,
+			});
+			return true;
+		}
+		false
+	}
+
+	/// Sends the motion of the cursor to the current grab.
+	fn update_grab(&self, comfy_kernel: &mut ComfyKernel) {
+		let (cursor_x, cursor_y) = comfy_kernel.get_cursor_coordinates();
+		match comfy_kernel.pointer_grab.clone().map(|pointer_grab| pointer_grab.kind) {
+			Some(PointerGrabKind::ResizeSplit {
+				output_name,
+				workspace_number,
+				split,
+			}) => comfy_kernel.move_split_to_cursor(&output_name, workspace_number, &split),
+			Some(PointerGrabKind::MoveFloatingWindow {
+				shell_handle,
+				cursor_origin: (origin_x, origin_y),
+				initial_area,
+			}) => {
+				let mut area = initial_area.clone();
+				area.origin.x += (cursor_x - origin_x) as i32;
+				area.origin.y += (cursor_y - origin_y) as i32;
+				if !comfy_kernel.set_floating_window_area(&shell_handle, area, false) {
+					comfy_kernel.end_pointer_grab();
+				}
+			}
+			Some(PointerGrabKind::ResizeFloatingWindow {
+				shell_handle,
+				cursor_origin: (origin_x, origin_y),
+				initial_area,
+				edges,
+			}) => {
+				let delta_x = (cursor_x - origin_x) as i32;
+				let delta_y = (cursor_y - origin_y) as i32;
+				let area = resize_area_from_edges(&initial_area, edges, delta_x, delta_y);
+				if !comfy_kernel.set_floating_window_area(&shell_handle, area, true) {
+					comfy_kernel.end_pointer_grab();
+				}
+			}
+			None => {}
+		}
+	}
+}
+
+/// Returns the edges of the area closest to the provided point, one horizontal and one vertical.
+fn nearest_edges(area: &Area, (x, y): (f64, f64)) -> u32 {
+	let center_x = f64::from(area.origin.x) + f64::from(area.size.width) / 2.0;
+	let center_y = f64::from(area.origin.y) + f64::from(area.size.height) / 2.0;
+	let horizontal_edge = if x < center_x {
+		wlr_edges::WLR_EDGE_LEFT
+	} else {
+		wlr_edges::WLR_EDGE_RIGHT
+	};
+	let vertical_edge = if y < center_y {
+		wlr_edges::WLR_EDGE_TOP
+	} else {
+		wlr_edges::WLR_EDGE_BOTTOM
+	};
+	horizontal_edge as u32 | vertical_edge as u32
+}
+
+/// Returns the name of the xcursor image showing a resize from the provided edges.
+pub fn resize_cursor_name(edges: u32) -> &'static str {
+	let top = edges & wlr_edges::WLR_EDGE_TOP as u32 != 0;
+	let bottom = edges & wlr_edges::WLR_EDGE_BOTTOM as u32 != 0;
+	let left = edges & wlr_edges::WLR_EDGE_LEFT as u32 != 0;
+	let right = edges & wlr_edges::WLR_EDGE_RIGHT as u32 != 0;
+	match (top, bottom, left, right) {
+		(true, _, true, _) => "top_left_corner",
+		(true, _, _, true) => "top_right_corner",
+		(_, true, true, _) => "bottom_left_corner",
+		(_, true, _, true) => "bottom_right_corner",
+		(true, _, _, _) => "top_side",
+		(_, true, _, _) => "bottom_side",
+		(_, _, true, _) => "left_side",
+		(_, _, _, true) => "right_side",
+		_ => "fleur",
+	}
+}
+
+/// Moves the provided edges of the area by the delta of the cursor, the opposite edges stay in place.
+fn resize_area_from_edges(initial_area: &Area, edges: u32, delta_x: i32, delta_y: i32) -> Area {
+	let mut area = initial_area.clone();
+	if edges & wlr_edges::WLR_EDGE_LEFT as u32 != 0 {
+		area.size.width = (initial_area.size.width - delta_x).max(MIN_FLOATING_WINDOW_SIZE);
+		area.origin.x = initial_area.origin.x + initial_area.size.width - area.size.width;
+	} else if edges & wlr_edges::WLR_EDGE_RIGHT as u32 != 0 {
+		area.size.width = (initial_area.size.width + delta_x).max(MIN_FLOATING_WINDOW_SIZE);
+	}
+	if edges & wlr_edges::WLR_EDGE_TOP as u32 != 0 {
+		area.size.height = (initial_area.size.height - delta_y).max(MIN_FLOATING_WINDOW_SIZE);
+		area.origin.y = initial_area.origin.y + initial_area.size.height - area.size.height;
+	} else if edges & wlr_edges::WLR_EDGE_BOTTOM as u32 != 0 {
+		area.size.height = (initial_area.size.height + delta_y).max(MIN_FLOATING_WINDOW_SIZE);
+	}
+	area
+}
+
+/// Returns the direction of a scroll step, the negative deltas going up or to the left.
+fn scroll_input(orientation: wlr_axis_orientation, delta: f64) -> MouseInput {
+	match (orientation, delta < 0.0) {
+		(wlr_axis_orientation::WLR_AXIS_ORIENTATION_VERTICAL, true) => MouseInput::ScrollUp,
+		(wlr_axis_orientation::WLR_AXIS_ORIENTATION_VERTICAL, false) => MouseInput::ScrollDown,
+		(wlr_axis_orientation::WLR_AXIS_ORIENTATION_HORIZONTAL, true) => MouseInput::ScrollLeft,
+		(wlr_axis_orientation::WLR_AXIS_ORIENTATION_HORIZONTAL, false) => MouseInput::ScrollRight,
+	}
+}
+
 impl WLRPointerHandler for PointerHandler {
 	#[wlroots_dehandle(compositor)]
 	fn on_motion_absolute(
@@ -24,6 +231,13 @@ impl WLRPointerHandler for PointerHandler {
 		let comfy_kernel: &mut ComfyKernel = compositor.into();
 
 		comfy_kernel.warp_cursor(event);
+		comfy_kernel.update_active_output_under_cursor();
+
+		// ? While grabbing, the motion only affects the grab
+		if comfy_kernel.pointer_grab.is_some() {
+			self.update_grab(comfy_kernel);
+			return;
+		}
 
 		if comfy_kernel.config.global.pointer_focus_type == PointerFocusType::OnHover {
 			comfy_kernel.apply_focus_under_cursor();
@@ -38,9 +252,13 @@ impl WLRPointerHandler for PointerHandler {
 		use compositor_handle as compositor;
 		let comfy_kernel: &mut ComfyKernel = compositor.into();
 		let (delta_x, delta_y) = event.delta();
-		let cursor_handle = &comfy_kernel.cursor_handle;
-		use cursor_handle as cursor;
-		cursor.move_to(event.device(), delta_x, delta_y)
+		{
+			let cursor_handle = &comfy_kernel.cursor_handle;
+			use cursor_handle as cursor;
+			cursor.move_to(event.device(), delta_x, delta_y);
+		}
+		comfy_kernel.update_active_output_under_cursor();
+		self.update_grab(comfy_kernel);
 	}
 
 	#[wlroots_dehandle(compositor)]
@@ -48,13 +266,51 @@ impl WLRPointerHandler for PointerHandler {
 		use compositor_handle as compositor;
 
 		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		let button = button_event.button();
+		let state = button_event.state();
+
+		let duration = Duration::from_millis(button_event.time_msec() as u64);
+
+		if state == wlr_button_state::WLR_BUTTON_PRESSED {
+			comfy_kernel.held_buttons.push(button);
+		} else {
+			comfy_kernel.held_buttons.retain(|held_button| *held_button != button);
+		}
+
+		// ? A grab ends when the button which started it is released, the other buttons are kept from the clients
+		if let Some(pointer_grab) = comfy_kernel.pointer_grab.clone() {
+			if state == wlr_button_state::WLR_BUTTON_RELEASED && button == pointer_grab.button {
+				comfy_kernel.end_pointer_grab();
+				// ? Otherwise the button would stay pressed for the client which requested the grab
+				if pointer_grab.is_requested_by_client {
+					comfy_kernel.transfer_click_to_seat(duration, button, state as u32);
+				}
+			}
+			return;
+		}
+		if state == wlr_button_state::WLR_BUTTON_PRESSED {
+			if let Some(command) = comfy_kernel.command_for_mouse_input(MouseInput::Button(button)) {
+				CommandInterpreter::execute(&command, comfy_kernel).ok();
+				comfy_kernel.pressed_bound_buttons.push(button);
+				return;
+			}
+		} else if let Some(index) = comfy_kernel
+			.pressed_bound_buttons
+			.iter()
+			.position(|pressed| *pressed == button)
+		{
+			// ? The clients never saw the button being pressed
+			comfy_kernel.pressed_bound_buttons.remove(index);
+			return;
+		}
+		if state == wlr_button_state::WLR_BUTTON_PRESSED && self.try_to_start_grab(comfy_kernel, button) {
+			return;
+		}
+
 		if comfy_kernel.config.global.pointer_focus_type == PointerFocusType::OnClick {
 			comfy_kernel.apply_focus_under_cursor();
 		}
 
-		let button = button_event.button();
-		let state = button_event.state();
-		let duration = Duration::from_millis(button_event.time_msec() as u64);
 		comfy_kernel.transfer_click_to_seat(duration, button, state as u32)
 	}
 
@@ -67,6 +323,36 @@ impl WLRPointerHandler for PointerHandler {
 		let orientation = axis_event.orientation();
 		let value = axis_event.delta();
 		let source = axis_event.source();
+
+		let command_option = if value != 0.0 {
+			comfy_kernel.command_for_mouse_input(scroll_input(orientation, value))
+		} else {
+			None
+		};
+		if source == wlr_axis_source::WLR_AXIS_SOURCE_WHEEL {
+			if let Some(command) = command_option {
+				CommandInterpreter::execute(&command, comfy_kernel).ok();
+				return;
+			}
+		} else {
+			// ? The other sources scroll continuously, a step is counted once they scrolled as far as a wheel click
+			let scroll_distance = self.scroll_distance_mut(orientation);
+			match command_option {
+				Some(command) => {
+					if *scroll_distance * value < 0.0 {
+						*scroll_distance = 0.0;
+					}
+					*scroll_distance += value;
+					if scroll_distance.abs() >= SCROLL_STEP_DISTANCE {
+						*scroll_distance -= value.signum() * SCROLL_STEP_DISTANCE;
+						CommandInterpreter::execute(&command, comfy_kernel).ok();
+					}
+					return;
+				}
+				// ? The scrolling stopped, or the keys of the binding were released
+				None => *scroll_distance = 0.0,
+			}
+		}
 		comfy_kernel.transfer_scroll_to_seat(duration, orientation, value, value as i32, source)
 	}
 }
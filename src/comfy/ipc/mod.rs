@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::prelude::*;
+use std::io::ErrorKind;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process;
+use std::ptr;
+
+use serde_json;
+use wayland_sys::server::{wl_event_loop, wl_event_source, WAYLAND_SERVER_HANDLE};
+use wlroots::{compositor_handle as wlr_compositor_handle, Area};
+
+use common::ipc::{AreaInfo, IpcEvent, IpcEventType, IpcReply, IpcRequest, IPC_SOCKET_ENV_VAR};
+use compositor::commands::interpreter::CommandInterpreter;
+use compositor::commands::Command;
+use compositor::ComfyKernel;
+
+pub mod query;
+
+/// The file descriptor is readable.
+const WL_EVENT_READABLE: u32 = 0x01;
+/// The file descriptor is writable.
+const WL_EVENT_WRITABLE: u32 = 0x02;
+/// The other end of the file descriptor hung up.
+const WL_EVENT_HANGUP: u32 = 0x04;
+/// An error occured on the file descriptor.
+const WL_EVENT_ERROR: u32 = 0x08;
+
+/// Size of the chunks read from the client sockets.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Bytes a client can leave unread before it's disconnected, so a subscriber which stopped reading can't grow the
+/// memory of the compositor forever.
+const MAX_PENDING_OUTPUT_SIZE: usize = 1024 * 1024;
+
+/*
+.######..#####....####............####...######..#####...##..##..######..#####..
+...##....##..##..##..##..........##......##......##..##..##..##..##......##..##.
+...##....#####...##...............####...####....#####...##..##..####....#####..
+...##....##......##..##..............##..##......##..##...####...##......##..##.
+.######..##.......####............####...######..##..##....##....######..##..##.
+................................................................................
+*/
+
+/// A client connected to the IPC socket.
+struct IpcClient {
+	stream: UnixStream,
+	event_source: *mut wl_event_source,
+	/// Bytes received from the client that don't form a complete line yet.
+	pending_bytes: Vec<u8>,
+	/// Bytes of the replies the socket couldn't accept yet, sent once it's writable again.
+	pending_output: Vec<u8>,
+	/// The client is polled for writability because some of it's output is pending.
+	is_waiting_for_writable: bool,
+	/// Events the client subscribed to, `None` until it sends a subscribe request.
+	subscribed_event_types: Option<Vec<IpcEventType>>,
+}
+
+impl IpcClient {
+	fn is_subscribed_to(&self, event_type: &IpcEventType) -> bool {
+		match self.subscribed_event_types {
+			Some(ref event_types) => event_types.is_empty() || event_types.contains(event_type),
+			None => false,
+		}
+	}
+
+	/// Writes as much of the pending output as the socket accepts, then polls the socket for writability only if some
+	/// output is left. Returns an error if the socket failed.
+	fn flush_pending_output(&mut self) -> Result<(), String> {
+		while !self.pending_output.is_empty() {
+			match self.stream.write(&self.pending_output) {
+				Ok(0) => return Err("The IPC client doesn't accept any more bytes".to_string()),
+				Ok(nb_bytes_written) => {
+					self.pending_output.drain(..nb_bytes_written);
+				}
+				Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+				Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+				Err(e) => return Err(format!("Could not write to the IPC client: {}", e)),
+			}
+		}
+
+		let should_wait_for_writable = !self.pending_output.is_empty();
+		if should_wait_for_writable != self.is_waiting_for_writable {
+			let mask = if should_wait_for_writable {
+				WL_EVENT_READABLE | WL_EVENT_WRITABLE
+			} else {
+				WL_EVENT_READABLE
+			};
+			unsafe {
+				ffi_dispatch!(
+					WAYLAND_SERVER_HANDLE,
+					wl_event_source_fd_update,
+					self.event_source,
+					mask
+				);
+			}
+			self.is_waiting_for_writable = should_wait_for_writable;
+		}
+		Ok(())
+	}
+}
+
+/// Unix domain socket server that lets external programs (scripts, bars, test harnesses) drive the compositor.
+/// The sockets are polled by the compositor's event loop, so every request is handled on the compositor thread.
+/// The path of the socket is advertised to the children of the compositor through `COMFYWM_SOCK`.
+pub struct IpcServer {
+	listener: UnixListener,
+	socket_path: PathBuf,
+	event_loop: *mut wl_event_loop,
+	listener_event_source: *mut wl_event_source,
+	clients: HashMap<RawFd, IpcClient>,
+}
+
+impl IpcServer {
+	/// Binds the IPC socket and registers it in the provided event loop.
+	pub fn start(event_loop: *mut wl_event_loop) -> Result<Self, String> {
+		let socket_path = generate_socket_path();
+		// ? A socket file left by a crashed instance with the same pid would prevent the bind
+		let _ = fs::remove_file(&socket_path);
+		let listener = UnixListener::bind(&socket_path)
+			.map_err(|e| format!("Could not bind the IPC socket {}: {}", socket_path.display(), e))?;
+		listener
+			.set_nonblocking(true)
+			.map_err(|e| format!("Could not set the IPC socket as non blocking: {}", e))?;
+
+		let listener_event_source = unsafe {
+			ffi_dispatch!(
+				WAYLAND_SERVER_HANDLE,
+				wl_event_loop_add_fd,
+				event_loop,
+				listener.as_raw_fd(),
+				WL_EVENT_READABLE,
+				handle_listener_event,
+				ptr::null_mut()
+			)
+		};
+		if listener_event_source.is_null() {
+			return Err("Could not add the IPC socket to the event loop".to_string());
+		}
+
+		env::set_var(IPC_SOCKET_ENV_VAR, &socket_path);
+		info!("IPC server listening on {}", socket_path.display());
+		Ok(IpcServer {
+			listener,
+			socket_path,
+			event_loop,
+			listener_event_source,
+			clients: HashMap::new(),
+		})
+	}
+
+	/// Accepts all the clients waiting on the socket and registers them in the event loop.
+	fn accept_pending_clients(&mut self) {
+		loop {
+			match self.listener.accept() {
+				Ok((stream, _)) => {
+					if let Err(e) = stream.set_nonblocking(true) {
+						error!("Could not set the IPC client socket as non blocking: {}", e);
+						continue;
+					}
+					let fd = stream.as_raw_fd();
+					let event_source = unsafe {
+						ffi_dispatch!(
+							WAYLAND_SERVER_HANDLE,
+							wl_event_loop_add_fd,
+							self.event_loop,
+							fd,
+							WL_EVENT_READABLE,
+							handle_client_event,
+							ptr::null_mut()
+						)
+					};
+					if event_source.is_null() {
+						error!("Could not add an IPC client to the event loop");
+						continue;
+					}
+					debug!("New IPC client connected (fd: {})", fd);
+					self.clients.insert(
+						fd,
+						IpcClient {
+							stream,
+							event_source,
+							pending_bytes: Vec::new(),
+							pending_output: Vec::new(),
+							is_waiting_for_writable: false,
+							subscribed_event_types: None,
+						},
+					);
+				}
+				Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+				Err(e) => {
+					error!("Could not accept an IPC client: {}", e);
+					break;
+				}
+			}
+		}
+	}
+
+	/// Reads everything the client sent and returns the complete lines.
+	/// Returns an error if the client hung up or the socket failed.
+	fn read_client_lines(&mut self, fd: RawFd) -> Result<Vec<String>, String> {
+		let client = self
+			.clients
+			.get_mut(&fd)
+			.ok_or_else(|| format!("Received an event for an unknown IPC client (fd: {})", fd))?;
+		let mut buffer = [0; READ_CHUNK_SIZE];
+		loop {
+			match client.stream.read(&mut buffer) {
+				Ok(0) => return Err("The IPC client hung up".to_string()),
+				Ok(nb_bytes_read) => client.pending_bytes.extend_from_slice(&buffer[..nb_bytes_read]),
+				Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+				Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+				Err(e) => return Err(format!("Could not read from the IPC client: {}", e)),
+			}
+		}
+
+		let mut lines = Vec::new();
+		while let Some(index_of_line_end) = client.pending_bytes.iter().position(|&byte| byte == b'\n') {
+			let line_bytes: Vec<u8> = client.pending_bytes.drain(..=index_of_line_end).collect();
+			let line = String::from_utf8_lossy(&line_bytes).trim().to_string();
+			if !line.is_empty() {
+				lines.push(line);
+			}
+		}
+		Ok(lines)
+	}
+
+	/// Sends a reply to the client as a single line of json. What the socket doesn't accept right away is kept until
+	/// it's writable again, the clients are only disconnected if their socket failed or if they stopped reading.
+	fn send_reply(&mut self, fd: RawFd, reply: &IpcReply) {
+		let mut message = match serde_json::to_string(reply) {
+			Ok(message) => message,
+			Err(e) => {
+				error!("Could not serialize the IPC reply: {}", e);
+				return;
+			}
+		};
+		message.push('\n');
+		let write_result = match self.clients.get_mut(&fd) {
+			Some(client) => {
+				if client.pending_output.len() + message.len() > MAX_PENDING_OUTPUT_SIZE {
+					Err("The IPC client stopped reading it's replies".to_string())
+				} else {
+					client.pending_output.extend_from_slice(message.as_bytes());
+					client.flush_pending_output()
+				}
+			}
+			None => return,
+		};
+		if let Err(e) = write_result {
+			warn!("Could not send the IPC reply, disconnecting the client: {}", e);
+			self.remove_client(fd);
+		}
+	}
+
+	/// Sends the output left to the client once it's socket is writable again.
+	fn flush_client(&mut self, fd: RawFd) {
+		let flush_result = match self.clients.get_mut(&fd) {
+			Some(client) => client.flush_pending_output(),
+			None => return,
+		};
+		if let Err(e) = flush_result {
+			warn!("Could not send the IPC reply, disconnecting the client: {}", e);
+			self.remove_client(fd);
+		}
+	}
+
+	/// Registers the events the client wants to receive. An empty list subscribes to all events.
+	fn subscribe_client(&mut self, fd: RawFd, event_types: Vec<IpcEventType>) {
+		if let Some(client) = self.clients.get_mut(&fd) {
+			client.subscribed_event_types = Some(event_types);
+		}
+	}
+
+	/// Sends the event to every client subscribed to it.
+	pub fn broadcast_event(&mut self, event: &IpcEvent) {
+		let event_type = event.event_type();
+		let subscribed_client_fds: Vec<RawFd> = self
+			.clients
+			.iter()
+			.filter(|(_, client)| client.is_subscribed_to(&event_type))
+			.map(|(&fd, _)| fd)
+			.collect();
+		if subscribed_client_fds.is_empty() {
+			return;
+		}
+		let reply = IpcReply::Event { event: event.clone() };
+		for fd in subscribed_client_fds {
+			self.send_reply(fd, &reply);
+		}
+	}
+
+	/// Unregisters the client from the event loop and closes it's socket.
+	fn remove_client(&mut self, fd: RawFd) {
+		if let Some(client) = self.clients.remove(&fd) {
+			unsafe {
+				ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, client.event_source);
+			}
+			debug!("IPC client disconnected (fd: {})", fd);
+		}
+	}
+}
+
+impl Drop for IpcServer {
+	fn drop(&mut self) {
+		let client_fds: Vec<RawFd> = self.clients.keys().cloned().collect();
+		for fd in client_fds {
+			self.remove_client(fd);
+		}
+		unsafe {
+			ffi_dispatch!(
+				WAYLAND_SERVER_HANDLE,
+				wl_event_source_remove,
+				self.listener_event_source
+			);
+		}
+		let _ = fs::remove_file(&self.socket_path);
+	}
+}
+
+/// Returns the path of the socket, inside the user's runtime directory when there is one.
+fn generate_socket_path() -> PathBuf {
+	let runtime_directory = env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp".to_string());
+	PathBuf::from(runtime_directory).join(format!("comfywm-ipc.{}.sock", process::id()))
+}
+
+/// Converts an area of the compositor to it's IPC representation.
+pub fn area_info(area: &Area) -> AreaInfo {
+	AreaInfo {
+		x: area.origin.x,
+		y: area.origin.y,
+		width: area.size.width,
+		height: area.size.height,
+	}
+}
+
+/*
+.#####...######...####...##..##..######...####...######...####..
+.##..##..##......##..##..##..##..##......##........##....##.....
+.#####...####....##..##..##..##..####.....####.....##.....####..
+.##..##..##......##.##...##..##..##..........##....##........##.
+.##..##..######...##.##...####...######...####.....##.....####..
+................................................................
+*/
+
+/// Called by the event loop when clients are waiting to connect on the socket.
+unsafe extern "C" fn handle_listener_event(_fd: c_int, _mask: u32, _data: *mut c_void) -> c_int {
+	with_comfy_kernel(|comfy_kernel| {
+		if let Some(ref mut ipc_server) = comfy_kernel.ipc_server {
+			ipc_server.accept_pending_clients();
+		}
+	});
+	0
+}
+
+/// Called by the event loop when a client sent data, hung up, or can receive the rest of it's replies.
+unsafe extern "C" fn handle_client_event(fd: c_int, mask: u32, _data: *mut c_void) -> c_int {
+	with_comfy_kernel(|comfy_kernel| handle_client_requests(comfy_kernel, fd, mask));
+	0
+}
+
+/// Runs the provided function with the kernel of the running compositor.
+#[wlroots_dehandle(compositor)]
+pub fn with_comfy_kernel<F>(f: F)
+where
+	F: FnOnce(&mut ComfyKernel),
+{
+	if let Some(compositor_handle) = wlr_compositor_handle() {
+		use compositor_handle as compositor;
+		let comfy_kernel: &mut ComfyKernel = compositor.into();
+		f(comfy_kernel);
+	}
+}
+
+/// Reads the requests sent by the client, executes them, then sends back a reply for each one.
+fn handle_client_requests(comfy_kernel: &mut ComfyKernel, fd: RawFd, mask: u32) {
+	let read_result = match comfy_kernel.ipc_server {
+		Some(ref mut ipc_server) => {
+			if mask & WL_EVENT_WRITABLE != 0 {
+				ipc_server.flush_client(fd);
+			}
+			if mask & (WL_EVENT_READABLE | WL_EVENT_HANGUP | WL_EVENT_ERROR) == 0 {
+				return;
+			}
+			ipc_server.read_client_lines(fd)
+		}
+		None => return,
+	};
+	let (request_lines, client_hung_up) = match read_result {
+		Ok(request_lines) => (request_lines, mask & (WL_EVENT_HANGUP | WL_EVENT_ERROR) != 0),
+		Err(e) => {
+			debug!("{}", e);
+			(Vec::new(), true)
+		}
+	};
+
+	for request_line in request_lines.iter() {
+		let reply = handle_request(comfy_kernel, fd, request_line);
+		if let Some(ref mut ipc_server) = comfy_kernel.ipc_server {
+			ipc_server.send_reply(fd, &reply);
+		}
+	}
+
+	if client_hung_up {
+		if let Some(ref mut ipc_server) = comfy_kernel.ipc_server {
+			ipc_server.remove_client(fd);
+		}
+	}
+}
+
+/// Parses and executes a single request, then returns the reply to send to the client.
+fn handle_request(comfy_kernel: &mut ComfyKernel, fd: RawFd, request_line: &str) -> IpcReply {
+	match serde_json::from_str::<IpcRequest>(request_line) {
+		Err(e) => IpcReply::Error {
+			message: format!("Invalid request: {}", e),
+		},
+		Ok(IpcRequest::Command { command, args }) => {
+			match CommandInterpreter::execute(&Command::new_with_args(command, args), comfy_kernel) {
+				Ok(()) => IpcReply::Success,
+				Err(message) => IpcReply::Error { message },
+			}
+		}
+		Ok(IpcRequest::Query { query }) => query::answer_query(comfy_kernel, &query),
+		Ok(IpcRequest::Subscribe { events }) => {
+			if let Some(ref mut ipc_server) = comfy_kernel.ipc_server {
+				ipc_server.subscribe_client(fd, events);
+			}
+			IpcReply::Success
+		}
+	}
+}
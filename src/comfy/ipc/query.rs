@@ -0,0 +1,83 @@
+use common::ipc::{IpcQuery, IpcReply, OutputInfo, WorkspaceInfo, WorkspaceTreeInfo};
+use compositor::ComfyKernel;
+use ipc::area_info;
+
+/// Builds the reply to a query from the current state of the compositor.
+pub fn answer_query(comfy_kernel: &mut ComfyKernel, query: &IpcQuery) -> IpcReply {
+	match query {
+		IpcQuery::Outputs => IpcReply::Outputs {
+			outputs: get_outputs_info(comfy_kernel),
+		},
+		IpcQuery::Workspaces => IpcReply::Workspaces {
+			workspaces: get_workspaces_info(comfy_kernel),
+		},
+		IpcQuery::FocusedWindow => IpcReply::FocusedWindow {
+			window: comfy_kernel.get_active_window().map(|window| window.get_info()),
+		},
+		IpcQuery::LayoutTree => IpcReply::LayoutTree {
+			workspaces: get_workspace_trees_info(comfy_kernel),
+		},
+	}
+}
+
+/// Returns the names of the outputs sorted alphabetically, so the replies don't depend on the hashing order.
+fn get_sorted_output_names(comfy_kernel: &ComfyKernel) -> Vec<String> {
+	let mut output_names: Vec<String> = comfy_kernel.output_data_map.keys().cloned().collect();
+	output_names.sort();
+	output_names
+}
+
+fn get_outputs_info(comfy_kernel: &ComfyKernel) -> Vec<OutputInfo> {
+	get_sorted_output_names(comfy_kernel)
+		.into_iter()
+		.filter_map(|output_name| {
+			comfy_kernel.output_data_map.get(&output_name).map(|output_data| OutputInfo {
+				is_active: output_name == comfy_kernel.active_output_name,
+				area: area_info(&output_data.area),
+				active_workspace: output_data.active_workspace_number,
+				workspaces: output_data.workspaces.keys().cloned().collect(),
+				name: output_name,
+			})
+		}).collect()
+}
+
+fn get_workspaces_info(comfy_kernel: &ComfyKernel) -> Vec<WorkspaceInfo> {
+	let mut workspaces_info = Vec::new();
+	for output_name in get_sorted_output_names(comfy_kernel) {
+		let output_is_active = output_name == comfy_kernel.active_output_name;
+		if let Some(output_data) = comfy_kernel.output_data_map.get(&output_name) {
+			for workspace in output_data.workspaces.values() {
+				let is_visible = workspace.number == output_data.active_workspace_number;
+				workspaces_info.push(WorkspaceInfo {
+					number: workspace.number,
+					output: output_name.clone(),
+					is_visible,
+					is_focused: is_visible && output_is_active,
+					nb_windows: workspace.nb_windows(),
+				});
+			}
+		}
+	}
+	workspaces_info
+}
+
+fn get_workspace_trees_info(comfy_kernel: &ComfyKernel) -> Vec<WorkspaceTreeInfo> {
+	let mut workspace_trees_info = Vec::new();
+	for output_name in get_sorted_output_names(comfy_kernel) {
+		if let Some(output_data) = comfy_kernel.output_data_map.get(&output_name) {
+			for workspace in output_data.workspaces.values() {
+				workspace_trees_info.push(WorkspaceTreeInfo {
+					number: workspace.number,
+					output: output_name.clone(),
+					root: workspace.window_layout.get_tree_info(),
+					floating_windows: workspace
+						.floating_windows
+						.iter()
+						.map(|window| window.get_info())
+						.collect(),
+				});
+			}
+		}
+	}
+	workspace_trees_info
+}
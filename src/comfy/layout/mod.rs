@@ -1,8 +1,14 @@
 use std::collections::HashMap;
+use std::f32;
+use std::str::FromStr;
 
-use wlroots::{Area, Origin, Size, XdgV6ShellSurfaceHandle as WLRXdgV6ShellSurfaceHandle};
+use wlroots::{Area, Origin, Size};
 
+use common::ipc::{LayoutAxisInfo, LayoutNodeInfo};
+use compositor::shell_handle::ShellHandle;
 use compositor::window::Window;
+use config::animations::{AnimationEvent, Animations};
+use ipc::area_info;
 
 /*
 ..####....####...##..##...####...######...####...##..##..######...####..
@@ -19,6 +25,9 @@ type NodeIndex = usize;
 /// The root of the layout is always the first node in the list.
 const INDEX_OF_ROOT: NodeIndex = 0;
 
+/// Smallest weight a node can be shrunk to, prevents windows from collapsing.
+const MIN_NODE_WEIGHT: f32 = 0.1;
+
 /// Axis on which a layout node operates.
 #[derive(Clone, Eq, PartialEq)]
 pub enum LayoutAxis {
@@ -81,6 +90,34 @@ impl LayoutDirection {
 		self.get_relative_position().as_linear_extremity()
 	}
 }
+impl FromStr for LayoutDirection {
+	type Err = String;
+
+	/// Parses a direction from it's name. (Example: `left`)
+	fn from_str(direction_str: &str) -> Result<Self, Self::Err> {
+		match direction_str.to_lowercase().as_str() {
+			"up" => Ok(LayoutDirection::Up),
+			"down" => Ok(LayoutDirection::Down),
+			"left" => Ok(LayoutDirection::Left),
+			"right" => Ok(LayoutDirection::Right),
+			_ => Err(format!(
+				"Invalid direction '{}', expected up, down, left or right",
+				direction_str
+			)),
+		}
+	}
+}
+
+/// Boundary between two adjacent children of a container node. Dragging it transfers weight between the two nodes.
+#[derive(Clone)]
+pub struct LayoutSplit {
+	/// Index of the container node.
+	container_index: NodeIndex,
+	/// Position, inside the container, of the child placed before the boundary.
+	position_of_child_before: usize,
+	/// Axis of the container, the boundary moves along it.
+	pub axis: LayoutAxis,
+}
 
 /// Relative linear position used when obtaining a node that is before or after another.
 pub enum RelativePosition {
@@ -335,6 +372,44 @@ impl Layout {
 		}
 	}
 
+	/// Returns `true` if the layout doesn't contain any window.
+	pub fn is_empty(&self) -> bool {
+		self.leaf_index_to_windows_map.is_empty()
+	}
+
+	/// Returns the number of windows contained in the layout.
+	pub fn nb_windows(&self) -> usize {
+		self.leaf_index_to_windows_map.len()
+	}
+
+	/// Returns a description of the layout tree from the root, used to answer IPC queries.
+	pub fn get_tree_info(&self) -> LayoutNodeInfo {
+		self.get_subtree_info(INDEX_OF_ROOT)
+	}
+
+	/// Recursive method that returns a description of the subtree from the provided root.
+	fn get_subtree_info(&self, subtree_root_index: NodeIndex) -> LayoutNodeInfo {
+		let subtree_root = self.layout_tree.nodes[subtree_root_index].as_ref().unwrap();
+		LayoutNodeInfo {
+			axis: match subtree_root.axis {
+				LayoutAxis::Vertical => LayoutAxisInfo::Vertical,
+				LayoutAxis::Horizontal => LayoutAxisInfo::Horizontal,
+			},
+			area: area_info(&subtree_root.area),
+			weight: subtree_root.weight,
+			is_active: subtree_root_index == self.layout_tree.active_node_index,
+			window: self
+				.leaf_index_to_windows_map
+				.get(&subtree_root_index)
+				.map(|window| window.get_info()),
+			children: subtree_root
+				.children_indices
+				.iter()
+				.map(|&child_index| self.get_subtree_info(child_index))
+				.collect(),
+		}
+	}
+
 	/// Returns `true` if the layout has a fullscreen window which is not currently doing an animation.
 	pub fn should_only_render_active_window(&self) -> bool {
 		if let Some(fullscreen_window_index) = self.fullscreen_window_index {
@@ -353,7 +428,7 @@ impl Layout {
 				let area_of_node = self.layout_tree.get_node_area(fullscreen_window_index).unwrap();
 				if let Some(fullscreen_window) = self.leaf_index_to_windows_map.get_mut(&fullscreen_window_index) {
 					fullscreen_window.toggle_fullscreen(false);
-					fullscreen_window.start_animation(area_of_node);
+					fullscreen_window.start_animation(area_of_node, AnimationEvent::WindowFullscreen);
 					self.fullscreen_window_index = None;
 				}
 			} else {
@@ -361,7 +436,7 @@ impl Layout {
 				let area_of_root = self.layout_tree.area().unwrap();
 				if let Some(active_leaf) = self.leaf_index_to_windows_map.get_mut(&active_node_index) {
 					active_leaf.toggle_fullscreen(true);
-					active_leaf.start_animation(area_of_root);
+					active_leaf.start_animation(area_of_root, AnimationEvent::WindowFullscreen);
 					self.fullscreen_window_index = Some(active_node_index);
 				}
 			}
@@ -427,7 +502,7 @@ impl Layout {
 	/// Updates the area of the layout then rebalances the tree from the root.
 	pub fn update_area_and_rebalance(&mut self, area: Area) {
 		self.update_area(area);
-		self.rebalance();
+		self.rebalance(AnimationEvent::WindowResize);
 	}
 
 	/// Returns the render area of the layout.
@@ -436,7 +511,7 @@ impl Layout {
 	}
 
 	/// Returns the shell handle of the active node if any.
-	pub fn get_active_shell_handle(&self) -> Option<WLRXdgV6ShellSurfaceHandle> {
+	pub fn get_active_shell_handle(&self) -> Option<ShellHandle> {
 		if let Some(Window { shell_handle, .. }) = self.leaf_index_to_windows_map.get(&self.layout_tree.active_node_index) {
 			Some(shell_handle.clone())
 		} else {
@@ -445,12 +520,12 @@ impl Layout {
 	}
 
 	/// Returns true if the provided shell is contained inside the layout.
-	pub fn contains_shell_handle(&self, shell_handle: &WLRXdgV6ShellSurfaceHandle) -> bool {
+	pub fn contains_shell_handle(&self, shell_handle: &ShellHandle) -> bool {
 		self.index_of_node_containing_shell_handle(shell_handle).is_some()
 	}
 
 	/// Returns the index of the node containing the provided xdg shell surface handle.
-	pub fn index_of_node_containing_shell_handle(&self, shell_handle: &WLRXdgV6ShellSurfaceHandle) -> Option<NodeIndex> {
+	pub fn index_of_node_containing_shell_handle(&self, shell_handle: &ShellHandle) -> Option<NodeIndex> {
 		let mut node_index_option = None;
 		for (&node_index, window) in self.leaf_index_to_windows_map.iter() {
 			if window.shell_handle == *shell_handle {
@@ -461,11 +536,16 @@ impl Layout {
 		node_index_option
 	}
 
+	/// Returns the window bound to the provided xdg shell surface handle if it's contained inside the layout.
+	pub fn get_window_from_shell_handle(&self, shell_handle: &ShellHandle) -> Option<&Window> {
+		self
+			.leaf_index_to_windows_map
+			.values()
+			.find(|window| window.shell_handle == *shell_handle)
+	}
+
 	/// Returns the shell_handle of the leaf node closest to the active one in a specific direction
-	pub fn get_shell_handle_relative_to_active_node(
-		&self,
-		direction: &LayoutDirection,
-	) -> Option<WLRXdgV6ShellSurfaceHandle> {
+	pub fn get_shell_handle_relative_to_active_node(&self, direction: &LayoutDirection) -> Option<ShellHandle> {
 		if self.layout_tree.active_node_is_root() {
 			None
 		} else {
@@ -481,15 +561,15 @@ impl Layout {
 		}
 	}
 
-	/// Rebalances the layout tree structure and applies the new sizes to each window.
-	fn rebalance(&mut self) {
+	/// Rebalances the layout tree structure and applies the new sizes to each window, with the animation of the event.
+	fn rebalance(&mut self, event: AnimationEvent) {
 		let indices_of_resized_nodes = self.layout_tree.rebalance();
 		for index_of_resized_node in indices_of_resized_nodes.iter() {
 			if let Some(window) = self.leaf_index_to_windows_map.get_mut(index_of_resized_node) {
 				// ? Rebalance doesn't affect fullscreen window
 				if !window.is_fullscreen {
 					let node_area = self.layout_tree.get_node_area(*index_of_resized_node).unwrap();
-					window.start_animation(node_area);
+					window.start_animation(node_area, event);
 				}
 			}
 		}
@@ -507,16 +587,35 @@ impl Layout {
 	/// Otherwise, it will be added as a child of the root.
 	pub fn add_shell_handle(
 		&mut self,
-		shell_handle: WLRXdgV6ShellSurfaceHandle,
+		shell_handle: ShellHandle,
 		direction: &LayoutDirection,
 		border_size: u8,
+		animations: &Animations,
 		set_as_last_activated: bool,
 		rebalance_after_insertion: bool,
 	) -> Result<(), String> {
 		// ? Add the top level shell as a new window
 		let default_area = self.layout_tree.get_default_node_area(direction);
 		let mut window = Window::new(shell_handle, default_area, border_size);
+		window.animations = animations.clone();
 		window.set_maximized();
+		self.add_window(window, direction, set_as_last_activated, rebalance_after_insertion)
+	}
+
+	/// Adds an existing window in the layout (Example: A window moved from another workspace).
+	/// The containing node will be a neighbor of the currently activated node if any.
+	/// Otherwise, it will be added as a child of the root.
+	pub fn add_window(
+		&mut self,
+		mut window: Window,
+		direction: &LayoutDirection,
+		set_as_last_activated: bool,
+		rebalance_after_insertion: bool,
+	) -> Result<(), String> {
+		// ? A window coming from another layout can't keep it's fullscreen state, the fullscreen index belongs to the layout
+		if window.is_fullscreen {
+			window.toggle_fullscreen(false);
+		}
 		let index_of_new_node = self
 			.layout_tree
 			.add_new_empty_node(LayoutAxis::Horizontal, INDEX_OF_ROOT);
@@ -535,7 +634,7 @@ impl Layout {
 			}
 			self.leaf_index_to_windows_map.insert(index_of_new_node, window);
 			if rebalance_after_insertion {
-				self.rebalance();
+				self.rebalance(AnimationEvent::WindowOpen);
 			}
 			self.layout_tree.print_to_console();
 		} else {
@@ -545,6 +644,22 @@ impl Layout {
 		Ok(())
 	}
 
+	/// Removes every window from the layout, which is left with an empty tree.
+	/// The windows are returned in the order their nodes were created.
+	pub fn take_all_windows(&mut self) -> Vec<Window> {
+		let mut leaf_indices: Vec<NodeIndex> = self.leaf_index_to_windows_map.keys().cloned().collect();
+		leaf_indices.sort();
+		let windows = leaf_indices
+			.iter()
+			.filter_map(|leaf_index| self.leaf_index_to_windows_map.remove(leaf_index))
+			.collect();
+		if let Some(area) = self.area() {
+			self.layout_tree = RegionBasedKAryLayoutTree::new(area);
+		}
+		self.fullscreen_window_index = None;
+		windows
+	}
+
 	/// Returns true if the layout contains a fullscreen window
 	pub fn has_fullscreen_window(&self) -> bool {
 		self.fullscreen_window_index.is_some()
@@ -560,7 +675,51 @@ impl Layout {
 		}
 
 		self.layout_tree.move_active_node(direction);
-		self.rebalance();
+		self.rebalance(AnimationEvent::WindowMove);
+	}
+
+	/// Grows (positive amount) or shrinks (negative amount) the active window along the axis of the provided direction.
+	pub fn resize_active_window(&mut self, direction: &LayoutDirection, amount: f32) {
+		if self.has_fullscreen_window() {
+			return;
+		}
+		if self
+			.layout_tree
+			.adjust_weight_of_active_node(&direction.get_axis(), amount)
+		{
+			self.rebalance(AnimationEvent::WindowResize);
+		}
+	}
+
+	/// Returns the split whose boundary is within `tolerance` pixels of the provided coordinates.
+	pub fn find_split_at(&self, x: f64, y: f64, tolerance: f64) -> Option<LayoutSplit> {
+		if self.has_fullscreen_window() {
+			return None;
+		}
+		self.layout_tree.find_split_at_point(x, y, tolerance)
+	}
+
+	/// Moves the boundary of the provided split to the coordinates, resizing the windows around it without animation
+	/// so that they follow the pointer.
+	pub fn move_split(&mut self, split: &LayoutSplit, x: f64, y: f64) {
+		if self.has_fullscreen_window() {
+			return;
+		}
+		let coordinate = match split.axis {
+			LayoutAxis::Horizontal => x,
+			LayoutAxis::Vertical => y,
+		};
+		if self.layout_tree.move_split(split, coordinate) {
+			let indices_of_resized_nodes = self.layout_tree.rebalance();
+			for index_of_resized_node in indices_of_resized_nodes.iter() {
+				if let Some(window) = self.leaf_index_to_windows_map.get_mut(index_of_resized_node) {
+					let node_area = self.layout_tree.get_node_area(*index_of_resized_node).unwrap();
+					if window.area != node_area {
+						window.resize(node_area);
+					}
+				}
+			}
+		}
 	}
 
 	/// Returns true if the provided node_index is associated with the fullscreen node
@@ -573,12 +732,12 @@ impl Layout {
 	}
 
 	/// If the layout contains a window associated with the provided xdg shell surface handle, we remove it from the layout.
-	/// Returns the node index of the root of the subtree that needs to be rebalanced.
+	/// Returns the removed window.
 	pub fn remove_window_from_shell_handle(
 		&mut self,
-		shell_handle: &WLRXdgV6ShellSurfaceHandle,
+		shell_handle: &ShellHandle,
 		rebalance_after_removal: bool,
-	) -> Result<(), String> {
+	) -> Result<Window, String> {
 		if let Some(index_of_node_containing_shell) = self.index_of_node_containing_shell_handle(shell_handle) {
 			if self.node_is_fullscreen(index_of_node_containing_shell) {
 				self.fullscreen_window_index = None;
@@ -591,23 +750,28 @@ impl Layout {
 			}
 
 			// ? Removes the window associated with the node index if any.
+			let mut removed_window_option = None;
 			for index_of_removed_leaf in removed_leaves.iter() {
-				self.leaf_index_to_windows_map.remove(&index_of_removed_leaf);
+				if let Some(removed_window) = self.leaf_index_to_windows_map.remove(index_of_removed_leaf) {
+					if *index_of_removed_leaf == index_of_node_containing_shell {
+						removed_window_option = Some(removed_window);
+					}
+				}
 			}
 
 			// ? Rebalance if desired
 			if rebalance_after_removal {
-				self.rebalance();
+				self.rebalance(AnimationEvent::WindowResize);
 			}
 			self.layout_tree.print_to_console();
-			Ok(())
+			removed_window_option.ok_or_else(|| "The removed node was not bound to a window".to_string())
 		} else {
 			return Err("Tried to remove a window which is not contained in the layout.".to_string());
 		}
 	}
 
 	/// Finds the node index associated with the shell_handle and sets it as the last activated node
-	pub fn set_as_last_activated(&mut self, shell_handle: &WLRXdgV6ShellSurfaceHandle) {
+	pub fn set_as_last_activated(&mut self, shell_handle: &ShellHandle) {
 		if let Some(node_index) = self.index_of_node_containing_shell_handle(shell_handle) {
 			self.layout_tree.set_as_last_activated(node_index);
 		}
@@ -1553,6 +1717,121 @@ impl RegionBasedKAryLayoutTree {
 		}
 	}
 
+	/// Adds the provided amount to the weight of the active node, or of it's nearest ancestor whose siblings are
+	/// laid out on the provided axis. The weight never goes below `MIN_NODE_WEIGHT`.
+	/// Returns true if a weight was changed.
+	pub fn adjust_weight_of_active_node(&mut self, axis: &LayoutAxis, amount: f32) -> bool {
+		let mut node_index = self.active_node_index;
+		while node_index != INDEX_OF_ROOT {
+			let parent_node_index = match self.get_parent_node_index_of(node_index) {
+				Some(parent_node_index) => parent_node_index,
+				None => return false,
+			};
+			let nb_siblings = self.get_direct_children_indices_of(parent_node_index).len();
+			if nb_siblings > 1 && self.node_is_on_same_axis(parent_node_index, axis) {
+				if let Some(Some(node)) = self.nodes.get_mut(node_index) {
+					let new_weight = (node.weight + amount).max(MIN_NODE_WEIGHT);
+					if new_weight == node.weight {
+						return false;
+					}
+					node.weight = new_weight;
+					return true;
+				}
+				return false;
+			}
+			node_index = parent_node_index;
+		}
+		false
+	}
+
+	/// Returns the split whose boundary is within `tolerance` pixels of the provided point.
+	/// Outer splits are checked before the splits of their children.
+	pub fn find_split_at_point(&self, x: f64, y: f64, tolerance: f64) -> Option<LayoutSplit> {
+		let mut indices_to_check = vec![INDEX_OF_ROOT];
+		while let Some(node_index) = indices_to_check.pop() {
+			if let Some(Some(node)) = self.nodes.get(node_index) {
+				if node.is_leaf() || !node.area.contains_point(x, y) {
+					continue;
+				}
+				let coordinate = match node.axis {
+					LayoutAxis::Horizontal => x,
+					LayoutAxis::Vertical => y,
+				};
+				// ? The last child has no boundary after it
+				for (position, &child_index) in node.children_indices.iter().enumerate().take(node.len() - 1) {
+					if let Some(Some(child_node)) = self.nodes.get(child_index) {
+						let boundary = match node.axis {
+							LayoutAxis::Horizontal => child_node.area.origin.x + child_node.area.size.width,
+							LayoutAxis::Vertical => child_node.area.origin.y + child_node.area.size.height,
+						};
+						if (coordinate - f64::from(boundary)).abs() <= tolerance {
+							return Some(LayoutSplit {
+								container_index: node_index,
+								position_of_child_before: position,
+								axis: node.axis.clone(),
+							});
+						}
+					}
+				}
+				indices_to_check.extend(node.children_indices.iter().cloned());
+			}
+		}
+		None
+	}
+
+	/// Moves the boundary of the split to the provided coordinate (along the axis of the split) by transfering weight
+	/// between the two nodes around it. Both nodes keep at least `MIN_NODE_WEIGHT`.
+	/// Returns true if the weights changed.
+	pub fn move_split(&mut self, split: &LayoutSplit, coordinate: f64) -> bool {
+		// ? The tree may have changed since the split was found, make sure it still exists
+		let (index_before, index_after) = match self.nodes.get(split.container_index) {
+			Some(Some(container)) if container.axis == split.axis => {
+				let children_indices = &container.children_indices;
+				match (
+					children_indices.get(split.position_of_child_before),
+					children_indices.get(split.position_of_child_before + 1),
+				) {
+					(Some(&index_before), Some(&index_after)) => (index_before, index_after),
+					_ => return false,
+				}
+			}
+			_ => return false,
+		};
+		let (node_before, node_after) = match (self.get_node_clone(index_before), self.get_node_clone(index_after)) {
+			(Some(node_before), Some(node_after)) => (node_before, node_after),
+			_ => return false,
+		};
+
+		let (start, combined_extent) = match split.axis {
+			LayoutAxis::Horizontal => (
+				node_before.area.origin.x,
+				node_before.area.size.width + node_after.area.size.width,
+			),
+			LayoutAxis::Vertical => (
+				node_before.area.origin.y,
+				node_before.area.size.height + node_after.area.size.height,
+			),
+		};
+		if combined_extent <= 0 {
+			return false;
+		}
+		let combined_weight = node_before.weight + node_after.weight;
+		let min_ratio = (MIN_NODE_WEIGHT / combined_weight).min(0.5);
+		let ratio_before = ((coordinate - f64::from(start)) / f64::from(combined_extent)) as f32;
+		let new_weight_before = combined_weight * ratio_before.max(min_ratio).min(1.0 - min_ratio);
+		if (new_weight_before - node_before.weight).abs() < f32::EPSILON {
+			return false;
+		}
+
+		if let Some(Some(node)) = self.nodes.get_mut(index_before) {
+			node.weight = new_weight_before;
+		}
+		if let Some(Some(node)) = self.nodes.get_mut(index_after) {
+			node.weight = combined_weight - new_weight_before;
+		}
+		true
+	}
+
 	/// Returns true if the node associated with the provided node index is on the same axis as the provided one.
 	fn node_is_on_same_axis(&self, node_index: NodeIndex, axis: &LayoutAxis) -> bool {
 		if let Some(node_axis) = self.get_axis_of(node_index) {
@@ -1692,3 +1971,69 @@ impl RegionBasedKAryLayoutTree {
 		indices_of_subtree
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Builds a tree whose root splits the provided area horizontally between the provided number of leaves.
+	fn generate_horizontal_tree(nb_leaves: usize) -> (RegionBasedKAryLayoutTree, Vec<NodeIndex>) {
+		let mut layout_tree = RegionBasedKAryLayoutTree::new(Area::new(Origin::new(0, 0), Size::new(1000, 500)));
+		let leaves: Vec<NodeIndex> = (0..nb_leaves)
+			.map(|_| {
+				let leaf_index = layout_tree.add_new_empty_node(LayoutAxis::Horizontal, INDEX_OF_ROOT);
+				layout_tree.move_index_under_root(leaf_index);
+				leaf_index
+			}).collect();
+		layout_tree.rebalance();
+		(layout_tree, leaves)
+	}
+
+	// adjust_weight_of_active_node
+
+	#[test]
+	fn adjust_weight_of_active_node_grows_the_active_leaf() {
+		let (mut layout_tree, leaves) = generate_horizontal_tree(2);
+		layout_tree.set_as_last_activated(leaves[0]);
+		assert!(layout_tree.adjust_weight_of_active_node(&LayoutAxis::Horizontal, 1.0));
+		layout_tree.rebalance();
+		assert!(layout_tree.get_node_area(leaves[0]).unwrap().size.width > 600);
+		assert!(layout_tree.get_node_area(leaves[1]).unwrap().size.width < 400);
+	}
+
+	#[test]
+	fn adjust_weight_of_active_node_ignores_other_axis() {
+		let (mut layout_tree, leaves) = generate_horizontal_tree(2);
+		layout_tree.set_as_last_activated(leaves[0]);
+		assert!(!layout_tree.adjust_weight_of_active_node(&LayoutAxis::Vertical, 1.0));
+	}
+
+	#[test]
+	fn adjust_weight_of_active_node_stops_at_min_weight() {
+		let (mut layout_tree, leaves) = generate_horizontal_tree(2);
+		layout_tree.set_as_last_activated(leaves[1]);
+		assert!(layout_tree.adjust_weight_of_active_node(&LayoutAxis::Horizontal, -10.0));
+		assert!(!layout_tree.adjust_weight_of_active_node(&LayoutAxis::Horizontal, -10.0));
+		layout_tree.rebalance();
+		assert!(layout_tree.get_node_area(leaves[1]).unwrap().size.width > 0);
+	}
+
+	// move_split
+
+	#[test]
+	fn move_split_transfers_weight_between_the_nodes_around_it() {
+		let (mut layout_tree, leaves) = generate_horizontal_tree(3);
+		let split = layout_tree.find_split_at_point(334.0, 250.0, 4.0).unwrap();
+		assert!(layout_tree.move_split(&split, 500.0));
+		layout_tree.rebalance();
+		assert!((layout_tree.get_node_area(leaves[0]).unwrap().size.width - 500).abs() <= 1);
+		assert!(layout_tree.get_node_area(leaves[1]).unwrap().size.width < 200);
+		assert_eq!(layout_tree.get_node_area(leaves[2]).unwrap().size.width, 334);
+	}
+
+	#[test]
+	fn find_split_at_point_ignores_points_far_from_boundaries() {
+		let (layout_tree, _) = generate_horizontal_tree(2);
+		assert!(layout_tree.find_split_at_point(250.0, 250.0, 4.0).is_none());
+	}
+}
@@ -20,10 +20,13 @@ extern crate chrono;
 extern crate common;
 extern crate libc;
 extern crate serde;
+extern crate serde_json;
 extern crate toml;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
+extern crate wayland_sys;
+#[macro_use]
 extern crate log;
 extern crate log4rs;
 extern crate strum;
@@ -36,6 +39,7 @@ use wlroots::utils::{init_logging as wlr_init_logging, WLR_DEBUG};
 mod compositor;
 mod config;
 mod input;
+mod ipc;
 mod layout;
 mod utils;
 
@@ -53,8 +57,6 @@ use utils::logger::{generate_log4rs_config, generate_wlroots_rs_log_callback};
 */
 
 fn main() {
-	// ? WIP: Required for x application to start, will be dynamically set if we wish to keep xwayland
-	// TODO: env::set_var("DISPLAY", ":1");
 	let log4rs_config = generate_log4rs_config();
 	// ? Use this handle to edit logging at runtime
 	let _handle = log4rs::init_config(log4rs_config).unwrap();
@@ -1,6 +1,6 @@
-/// Enum of all animation variants.
-#[derive(Clone)]
-#[allow(dead_code)]
+/// Enum of all animation variants, named in snake case in the config. (Example: `ease_in_out_circ`)
+#[derive(Clone, Debug, PartialEq, EnumString)]
+#[strum(serialize_all = "snake_case")]
 pub enum Animation {
 	Linear,
 	EaseInQuad,
@@ -3,7 +3,7 @@ use utils::animation::Animation;
 use utils::time::duration_to_millis;
 use wlroots::{Area, Origin, Size};
 
-/// Area transition animation from a source to a destination. The animation is controlled using a duration in seconds and an animation variant (linear, easing, etc).
+/// Area transition animation from a source to a destination. The animation is controlled using a duration and an animation variant (linear, easing, etc).
 /// The animation starts as soon as the object is created. The precision of the animation is handled by polling the animation object more frequently.
 #[derive(Clone)]
 pub struct AreaAnimation {
@@ -15,7 +15,7 @@ pub struct AreaAnimation {
 	transition: Animation,
 }
 impl AreaAnimation {
-	pub fn new(source: Area, destination: Area, duration_in_seconds: u8, transition: Animation) -> Self {
+	pub fn new(source: Area, destination: Area, duration: Duration, transition: Animation) -> Self {
 		let area_diff = Area::new(
 			Origin::new(
 				destination.origin.x - source.origin.x,
@@ -31,7 +31,7 @@ impl AreaAnimation {
 			destination,
 			area_diff,
 			start_time: Instant::now(),
-			duration: Duration::new(0, duration_in_seconds as u32 * 1000000),
+			duration,
 			transition,
 		}
 	}
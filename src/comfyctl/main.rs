@@ -0,0 +1,307 @@
+/*
+..####....####...##...##..######..##..##...####...######..##.....
+.##..##..##..##..###.###..##.......####...##..##....##....##.....
+.##......##..##..##.#.##..####......##....##........##....##.....
+.##..##..##..##..##...##..##........##....##..##....##....##.....
+..####....####...##...##..##........##.....####.....##....######.
+.................................................................
+
+	Command-line client of the compositor's IPC socket.
+
+	Usage:
+		comfyctl [--socket <path>] [--json] <command> [args...]
+		comfyctl [--socket <path>] [--json] query <outputs|workspaces|focused_window|layout_tree>
+		comfyctl [--socket <path>] [--json] subscribe [events...]
+*/
+
+extern crate common;
+extern crate serde_json;
+
+use std::env;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::os::unix::net::UnixStream;
+use std::process;
+use std::str::FromStr;
+
+use common::command_type::CommandType;
+use common::ipc::{
+	AreaInfo, IpcEvent, IpcEventType, IpcQuery, IpcReply, IpcRequest, LayoutNodeInfo, OutputInfo, WindowInfo,
+	WorkspaceInfo, WorkspaceTreeInfo, IPC_SOCKET_ENV_VAR,
+};
+
+const USAGE: &str = "Usage:
+	comfyctl [--socket <path>] [--json] <command> [args...]
+	comfyctl [--socket <path>] [--json] query <outputs|workspaces|focused_window|layout_tree>
+	comfyctl [--socket <path>] [--json] subscribe [events...]
+
+Events:
+	window_mapped, window_destroyed, focus_changed, output_added, output_removed, config_reloaded, fullscreen_toggled,
+	mode_changed";
+
+/// Options parsed from the command line.
+struct Options {
+	socket_path: Option<String>,
+	print_json: bool,
+	request: IpcRequest,
+}
+
+/*
+.##...##...####...######..##..##.
+.###.###..##..##....##....###.##.
+.##.#.##..######....##....##.###.
+.##...##..##..##....##....##..##.
+.##...##..##..##..######..##..##.
+.................................
+*/
+
+fn main() {
+	let options = match parse_options(env::args().skip(1).collect()) {
+		Ok(options) => options,
+		Err(e) => {
+			eprintln!("{}\n\n{}", e, USAGE);
+			process::exit(2);
+		}
+	};
+
+	let socket_path = match options.socket_path.or_else(|| env::var(IPC_SOCKET_ENV_VAR).ok()) {
+		Some(socket_path) => socket_path,
+		None => {
+			eprintln!(
+				"{} is not set, is comfywm running? (The socket can also be provided with --socket)",
+				IPC_SOCKET_ENV_VAR
+			);
+			process::exit(1);
+		}
+	};
+
+	let mut reply_reader = match send_request(&socket_path, &options.request) {
+		Ok(reply_reader) => reply_reader,
+		Err(e) => {
+			eprintln!("{}", e);
+			process::exit(1);
+		}
+	};
+
+	// ? Subscriptions keep printing the events until the compositor closes the connection
+	let is_subscription = match options.request {
+		IpcRequest::Subscribe { .. } => true,
+		_ => false,
+	};
+	loop {
+		match read_reply(&mut reply_reader) {
+			Ok(reply) => {
+				if options.print_json {
+					println!("{}", serde_json::to_string_pretty(&reply).unwrap());
+				} else {
+					print_reply(&reply);
+				}
+				if let IpcReply::Error { .. } = reply {
+					process::exit(1);
+				}
+			}
+			Err(e) => {
+				eprintln!("{}", e);
+				process::exit(1);
+			}
+		}
+		if !is_subscription {
+			break;
+		}
+	}
+}
+
+/// Builds the request to send from the arguments of the program.
+fn parse_options(args: Vec<String>) -> Result<Options, String> {
+	let mut socket_path = None;
+	let mut print_json = false;
+	let mut args = args.into_iter();
+	let mut positional_args = Vec::new();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--socket" if positional_args.is_empty() => {
+				socket_path = Some(args.next().ok_or("Missing the path after --socket")?);
+			}
+			"--json" if positional_args.is_empty() => print_json = true,
+			"-h" | "--help" if positional_args.is_empty() => {
+				println!("{}", USAGE);
+				process::exit(0);
+			}
+			_ => positional_args.push(arg),
+		}
+	}
+
+	if positional_args.is_empty() {
+		return Err("No command was provided".to_string());
+	}
+	let name = positional_args.remove(0);
+	let request = if name == "query" {
+		if positional_args.len() != 1 {
+			return Err("The query command expects a single query name".to_string());
+		}
+		let query =
+			IpcQuery::from_str(&positional_args[0]).map_err(|_| format!("Unknown query: {}", positional_args[0]))?;
+		IpcRequest::Query { query }
+	} else if name == "subscribe" {
+		let mut events = Vec::new();
+		for event_name in positional_args.iter() {
+			events.push(IpcEventType::from_str(event_name).map_err(|_| format!("Unknown event: {}", event_name))?);
+		}
+		IpcRequest::Subscribe { events }
+	} else {
+		let command = CommandType::from_str(&name).map_err(|_| format!("Unknown command: {}", name))?;
+		IpcRequest::Command {
+			command,
+			args: positional_args,
+		}
+	};
+
+	Ok(Options {
+		socket_path,
+		print_json,
+		request,
+	})
+}
+
+/// Sends the request on the socket, then returns a reader for the replies of the compositor.
+fn send_request(socket_path: &str, request: &IpcRequest) -> Result<BufReader<UnixStream>, String> {
+	let mut stream =
+		UnixStream::connect(socket_path).map_err(|e| format!("Could not connect to {}: {}", socket_path, e))?;
+	let mut message = serde_json::to_string(request).map_err(|e| format!("Could not serialize the request: {}", e))?;
+	message.push('\n');
+	stream
+		.write_all(message.as_bytes())
+		.map_err(|e| format!("Could not send the request: {}", e))?;
+	Ok(BufReader::new(stream))
+}
+
+/// Waits for the next reply of the compositor.
+fn read_reply(reply_reader: &mut BufReader<UnixStream>) -> Result<IpcReply, String> {
+	let mut reply_line = String::new();
+	let nb_bytes_read = reply_reader
+		.read_line(&mut reply_line)
+		.map_err(|e| format!("Could not read the reply: {}", e))?;
+	if nb_bytes_read == 0 {
+		return Err("The compositor closed the connection".to_string());
+	}
+	serde_json::from_str(&reply_line).map_err(|e| format!("Invalid reply from the compositor: {}", e))
+}
+
+/*
+.#####...######...####...#####...##.......####...##..##.
+.##..##....##....##......##..##..##......##..##...####..
+.##..##....##.....####...#####...##......######....##...
+.##..##....##........##..##......##......##..##....##...
+.#####...######...####...##......######..##..##....##...
+........................................................
+*/
+
+fn print_reply(reply: &IpcReply) {
+	match reply {
+		IpcReply::Success => {}
+		IpcReply::Error { message } => eprintln!("Error: {}", message),
+		IpcReply::Outputs { outputs } => outputs.iter().for_each(print_output),
+		IpcReply::Workspaces { workspaces } => workspaces.iter().for_each(print_workspace),
+		IpcReply::FocusedWindow { window } => match window {
+			Some(window) => print_window(window),
+			None => println!("No focused window"),
+		},
+		IpcReply::LayoutTree { workspaces } => workspaces.iter().for_each(print_workspace_tree),
+		IpcReply::Event { event } => print_event(event),
+	}
+}
+
+fn print_event(event: &IpcEvent) {
+	match event {
+		IpcEvent::WindowMapped { window } => println!("Window mapped: {}", format_window(window)),
+		IpcEvent::WindowDestroyed { window } => println!("Window destroyed: {}", format_window(window)),
+		IpcEvent::FocusChanged { window } => match window {
+			Some(window) => println!("Focus changed: {}", format_window(window)),
+			None => println!("Focus cleared"),
+		},
+		IpcEvent::OutputAdded { output } => println!("Output added: {}", output),
+		IpcEvent::OutputRemoved { output } => println!("Output removed: {}", output),
+		IpcEvent::ConfigReloaded => println!("Config reloaded"),
+		IpcEvent::FullscreenToggled { window } => println!(
+			"Fullscreen {}: {}",
+			if window.is_fullscreen { "enabled" } else { "disabled" },
+			format_window(window)
+		),
+		IpcEvent::ModeChanged { mode } => println!("Mode changed: {}", mode),
+	}
+}
+
+fn print_output(output: &OutputInfo) {
+	let workspaces: Vec<String> = output.workspaces.iter().map(|number| number.to_string()).collect();
+	println!(
+		"Output {}{}\n  Area: {}\n  Active workspace: {}\n  Workspaces: {}",
+		output.name,
+		if output.is_active { " (active)" } else { "" },
+		format_area(&output.area),
+		output.active_workspace,
+		workspaces.join(", ")
+	);
+}
+
+fn print_workspace(workspace: &WorkspaceInfo) {
+	let state = if workspace.is_focused {
+		" (focused)"
+	} else if workspace.is_visible {
+		" (visible)"
+	} else {
+		""
+	};
+	println!(
+		"Workspace {} on {}{}: {} window(s)",
+		workspace.number, workspace.output, state, workspace.nb_windows
+	);
+}
+
+fn print_window(window: &WindowInfo) {
+	println!(
+		"{}\n  App id: {}\n  Area: {}{}",
+		window.title,
+		window.app_id,
+		format_area(&window.area),
+		if window.is_fullscreen { "\n  Fullscreen" } else { "" }
+	);
+}
+
+fn print_workspace_tree(workspace_tree: &WorkspaceTreeInfo) {
+	println!("Workspace {} on {}", workspace_tree.number, workspace_tree.output);
+	print_layout_node(&workspace_tree.root, 1);
+	for window in workspace_tree.floating_windows.iter() {
+		println!("  Floating {} {}", format_window(window), format_area(&window.area));
+	}
+}
+
+/// Recursively prints the subtree, indenting each level.
+fn print_layout_node(node: &LayoutNodeInfo, depth: usize) {
+	let indentation = "  ".repeat(depth);
+	let active_marker = if node.is_active { " *" } else { "" };
+	match node.window {
+		Some(ref window) => println!(
+			"{}[{}] {} (weight: {}){}",
+			indentation, window.app_id, window.title, node.weight, active_marker
+		),
+		None => println!(
+			"{}{:?} {} (weight: {}){}",
+			indentation,
+			node.axis,
+			format_area(&node.area),
+			node.weight,
+			active_marker
+		),
+	}
+	for child in node.children.iter() {
+		print_layout_node(child, depth + 1);
+	}
+}
+
+fn format_window(window: &WindowInfo) -> String {
+	format!("#{} [{}] {}", window.id, window.app_id, window.title)
+}
+
+fn format_area(area: &AreaInfo) -> String {
+	format!("{}x{} at {},{}", area.width, area.height, area.x, area.y)
+}
@@ -7,8 +7,9 @@
 ..........................................................................................
 */
 
-#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString)]
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString, Serialize, Deserialize)]
 #[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum CommandType {
 	Terminate,
 	Exec,
@@ -29,4 +30,14 @@ pub enum CommandType {
 	ToggleActiveWindowFullscreen,
 	ReloadConfig,
 	CloseActiveWindow,
+	SwitchToWorkspace,
+	MoveActiveWindowToWorkspace,
+	GrowActiveWindow,
+	ShrinkActiveWindow,
+	ToggleActiveWindowFloating,
+	Screenshot,
+	FocusOutput,
+	MoveActiveWindowToOutput,
+	EnterMode,
+	SwitchKeyboardLayout,
 }
@@ -0,0 +1,170 @@
+/*
+.######..#####....####..
+...##....##..##..##..##.
+...##....#####...##.....
+...##....##......##..##.
+.######..##.......####..
+........................
+*/
+
+use command_type::CommandType;
+
+/// Name of the environment variable containing the path of the compositor's IPC socket.
+pub const IPC_SOCKET_ENV_VAR: &str = "COMFYWM_SOCK";
+
+/// Message sent by a client to the compositor. Each message is serialized as a single line of json.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcRequest {
+	/// Executes a command. (Example: `{"type": "command", "command": "exec", "args": ["foot"]}`)
+	Command {
+		command: CommandType,
+		#[serde(default)]
+		args: Vec<String>,
+	},
+	/// Queries the state of the compositor. (Example: `{"type": "query", "query": "outputs"}`)
+	Query { query: IpcQuery },
+	/// Subscribes the client to the provided events, or to all of them if none are provided.
+	/// The compositor then sends an `event` reply each time one of them occurs.
+	/// (Example: `{"type": "subscribe", "events": ["window_mapped", "focus_changed"]}`)
+	Subscribe {
+		#[serde(default)]
+		events: Vec<IpcEventType>,
+	},
+}
+
+/// Variants of the states that can be queried from the compositor.
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum IpcQuery {
+	Outputs,
+	Workspaces,
+	FocusedWindow,
+	LayoutTree,
+}
+
+/// Message sent by the compositor in response to each request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcReply {
+	Success,
+	Error { message: String },
+	Outputs { outputs: Vec<OutputInfo> },
+	Workspaces { workspaces: Vec<WorkspaceInfo> },
+	FocusedWindow { window: Option<WindowInfo> },
+	LayoutTree { workspaces: Vec<WorkspaceTreeInfo> },
+	Event { event: IpcEvent },
+}
+
+/// Variants of the events a client can subscribe to.
+#[derive(Clone, Debug, PartialEq, Eq, ToString, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum IpcEventType {
+	WindowMapped,
+	WindowDestroyed,
+	FocusChanged,
+	OutputAdded,
+	OutputRemoved,
+	ConfigReloaded,
+	FullscreenToggled,
+	ModeChanged,
+}
+
+/// Event sent by the compositor to the subscribed clients.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IpcEvent {
+	WindowMapped { window: WindowInfo },
+	WindowDestroyed { window: WindowInfo },
+	/// The keyboard focus moved to a window, or was cleared (`window` is null).
+	FocusChanged { window: Option<WindowInfo> },
+	OutputAdded { output: String },
+	OutputRemoved { output: String },
+	ConfigReloaded,
+	FullscreenToggled { window: WindowInfo },
+	/// The keybindings are now looked up in another binding mode.
+	ModeChanged { mode: String },
+}
+
+impl IpcEvent {
+	pub fn event_type(&self) -> IpcEventType {
+		match self {
+			IpcEvent::WindowMapped { .. } => IpcEventType::WindowMapped,
+			IpcEvent::WindowDestroyed { .. } => IpcEventType::WindowDestroyed,
+			IpcEvent::FocusChanged { .. } => IpcEventType::FocusChanged,
+			IpcEvent::OutputAdded { .. } => IpcEventType::OutputAdded,
+			IpcEvent::OutputRemoved { .. } => IpcEventType::OutputRemoved,
+			IpcEvent::ConfigReloaded => IpcEventType::ConfigReloaded,
+			IpcEvent::FullscreenToggled { .. } => IpcEventType::FullscreenToggled,
+			IpcEvent::ModeChanged { .. } => IpcEventType::ModeChanged,
+		}
+	}
+}
+
+/// Position and dimensions of an element of the compositor, in layout coordinates.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AreaInfo {
+	pub x: i32,
+	pub y: i32,
+	pub width: i32,
+	pub height: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutputInfo {
+	pub name: String,
+	pub area: AreaInfo,
+	pub is_active: bool,
+	pub active_workspace: u32,
+	pub workspaces: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+	pub number: u32,
+	pub output: String,
+	/// The workspace is the one displayed on it's output.
+	pub is_visible: bool,
+	/// The workspace is displayed on the active output.
+	pub is_focused: bool,
+	pub nb_windows: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowInfo {
+	/// Identifier of the window, unique for the lifetime of the compositor.
+	pub id: usize,
+	pub title: String,
+	pub app_id: String,
+	pub area: AreaInfo,
+	pub is_fullscreen: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutAxisInfo {
+	Vertical,
+	Horizontal,
+}
+
+/// A node of the layout tree. Leaves contain a window, containers contain children.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayoutNodeInfo {
+	pub axis: LayoutAxisInfo,
+	pub area: AreaInfo,
+	pub weight: f32,
+	pub is_active: bool,
+	pub window: Option<WindowInfo>,
+	pub children: Vec<LayoutNodeInfo>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkspaceTreeInfo {
+	pub number: u32,
+	pub output: String,
+	pub root: LayoutNodeInfo,
+	/// Windows displayed above the layout, from the bottom to the top.
+	pub floating_windows: Vec<WindowInfo>,
+}
@@ -4,6 +4,9 @@ extern crate strum_macros;
 #[macro_use]
 extern crate lazy_static;
 extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 /*
 ..####....####...##...##..##...##...####...##..##.
 .##..##..##..##..###.###..###.###..##..##..###.##.
@@ -15,3 +18,4 @@ extern crate regex;
 
 pub mod colors;
 pub mod command_type;
+pub mod ipc;